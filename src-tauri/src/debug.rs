@@ -0,0 +1,91 @@
+use std::sync::Mutex;
+
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::{Api, Patch, PatchParams};
+use serde_json::json;
+use tauri::Manager;
+
+use crate::error::AppError;
+use crate::settings::SettingsStore;
+use crate::watcher;
+
+/// Image of the debug container unless `debug_image` is set in settings.json.
+///
+/// VS Code Server needs glibc, so Alpine or BusyBox images can be used only by exec.
+pub const DEFAULT_DEBUG_IMAGE: &str = "debian:stable-slim";
+
+/// Prefix of the names of debug containers, same as `kubectl debug`
+const DEBUG_CONTAINER_NAME_PREFIX: &str = "debugger-";
+
+/// Add the ephemeral container to the pod to debug the container without a shell (ex: distroless).
+///
+/// The debug container shares the process namespace of `target_container_name` and runs until the pod is deleted.
+/// Returns the name of the debug container.
+#[tauri::command]
+pub async fn create_debug_container(
+    app_handle: tauri::AppHandle,
+    context: String,
+    namespace: String,
+    pod_name: String,
+    target_container_name: String,
+    image: Option<String>,
+) -> Result<String, AppError> {
+    let image = image
+        .map(|image| image.trim().to_string())
+        .filter(|image| !image.is_empty())
+        .unwrap_or_else(|| {
+            app_handle
+                .state::<Mutex<SettingsStore>>()
+                .lock()
+                .unwrap()
+                .app_settings()
+                .debug_image
+        });
+    let client = watcher::client(&context).await?;
+    let api: Api<Pod> = Api::namespaced(client, &namespace);
+    let pod = api.get(&pod_name).await?;
+    let spec = pod.spec.unwrap_or_default();
+    if !spec
+        .containers
+        .iter()
+        .any(|container| container.name == target_container_name)
+    {
+        return Err(AppError::NotFound(format!(
+            "{target_container_name} is not found in {pod_name}"
+        )));
+    }
+    let names: Vec<&str> = spec
+        .containers
+        .iter()
+        .map(|container| container.name.as_str())
+        .chain(
+            spec.ephemeral_containers
+                .iter()
+                .flatten()
+                .map(|container| container.name.as_str()),
+        )
+        .collect();
+    let name = (1..)
+        .map(|i| format!("{DEBUG_CONTAINER_NAME_PREFIX}{i}"))
+        .find(|name| !names.contains(&name.as_str()))
+        .unwrap();
+    // Keep the shell waiting on stdin so that the container keeps running without being attached
+    let patch = json!({
+        "spec": {
+            "ephemeralContainers": [{
+                "name": name,
+                "image": image,
+                "targetContainerName": target_container_name,
+                "stdin": true,
+                "tty": true,
+                "terminationMessagePolicy": "File",
+            }]
+        }
+    });
+    // ex: 404 if the cluster does not support ephemeral containers (before Kubernetes 1.23)
+    api.patch_ephemeral_containers(&pod_name, &PatchParams::default(), &Patch::Strategic(patch))
+        .await?;
+    println!("Created debug container {name} ({image}) in {pod_name}");
+    crate::refresh_cluster_statuses(&app_handle);
+    Ok(name)
+}
@@ -0,0 +1,164 @@
+use std::fmt;
+
+use kube::client::UpgradeConnectionError;
+use kube::config::KubeconfigError;
+use kube::error::ErrorResponse;
+use kube::runtime::watcher;
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+use crate::launcher::CommandNotFound;
+
+/// Error returned to the frontend.
+///
+/// Serialized as `{"code": "forbidden", "message": "...", "details": ...}` for the UI to show actionable messages.
+#[derive(Debug, Clone)]
+pub enum AppError {
+    /// kubeconfig is not found or invalid
+    KubeconfigMissing(String),
+    /// The context is not found in kubeconfig
+    ContextMissing(String),
+    /// Failed to authenticate to the API server
+    AuthFailed(String),
+    /// The API server is not reachable
+    ApiUnreachable(String),
+    /// The operation is forbidden by RBAC
+    Forbidden(String),
+    /// The command of the editor is not found in any candidate locations
+    EditorNotFound(CommandNotFound),
+    /// The editor failed to start or exited with an error
+    EditorFailed(String),
+    /// The terminal emulator failed to start or exited with an error
+    TerminalFailed(String),
+    /// The workspace folder does not exist in the container
+    PathNotFound(String),
+    ContainerNotRunning(String),
+    /// The local port to forward is used by another process
+    PortInUse(String),
+    /// Pod, container or workspace is not found
+    NotFound(String),
+    /// Settings or arguments given by the user are invalid
+    InvalidInput(String),
+    Other(String),
+}
+
+impl AppError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::KubeconfigMissing(_) => "kubeconfigMissing",
+            AppError::ContextMissing(_) => "contextMissing",
+            AppError::AuthFailed(_) => "authFailed",
+            AppError::ApiUnreachable(_) => "apiUnreachable",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::EditorNotFound(_) => "editorNotFound",
+            AppError::EditorFailed(_) => "editorFailed",
+            AppError::TerminalFailed(_) => "terminalFailed",
+            AppError::PathNotFound(_) => "pathNotFound",
+            AppError::ContainerNotRunning(_) => "containerNotRunning",
+            AppError::PortInUse(_) => "portInUse",
+            AppError::NotFound(_) => "notFound",
+            AppError::InvalidInput(_) => "invalidInput",
+            AppError::Other(_) => "other",
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::EditorNotFound(e) => write!(f, "{e}"),
+            AppError::KubeconfigMissing(message)
+            | AppError::ContextMissing(message)
+            | AppError::AuthFailed(message)
+            | AppError::ApiUnreachable(message)
+            | AppError::Forbidden(message)
+            | AppError::EditorFailed(message)
+            | AppError::TerminalFailed(message)
+            | AppError::PathNotFound(message)
+            | AppError::ContainerNotRunning(message)
+            | AppError::PortInUse(message)
+            | AppError::NotFound(message)
+            | AppError::InvalidInput(message)
+            | AppError::Other(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        match self {
+            AppError::EditorNotFound(e) => state.serialize_field("details", e)?,
+            _ => state.serialize_field("details", &None::<()>)?,
+        }
+        state.end()
+    }
+}
+
+impl From<ErrorResponse> for AppError {
+    fn from(e: ErrorResponse) -> Self {
+        match e.code {
+            401 => AppError::AuthFailed(e.message),
+            403 => AppError::Forbidden(e.message),
+            404 => AppError::NotFound(e.message),
+            _ => AppError::Other(e.message),
+        }
+    }
+}
+
+impl From<kube::Error> for AppError {
+    fn from(e: kube::Error) -> Self {
+        match e {
+            kube::Error::Api(e) => e.into(),
+            kube::Error::Auth(_) => AppError::AuthFailed(e.to_string()),
+            kube::Error::HyperError(_) | kube::Error::Service(_) => {
+                AppError::ApiUnreachable(e.to_string())
+            }
+            kube::Error::InferConfig(_) => AppError::KubeconfigMissing(e.to_string()),
+            kube::Error::UpgradeConnection(UpgradeConnectionError::ProtocolSwitch(status))
+                if status.as_u16() == 403 =>
+            {
+                AppError::Forbidden(e.to_string())
+            }
+            _ => AppError::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<watcher::Error> for AppError {
+    fn from(e: watcher::Error) -> Self {
+        match e {
+            watcher::Error::InitialListFailed(e)
+            | watcher::Error::WatchStartFailed(e)
+            | watcher::Error::WatchFailed(e) => e.into(),
+            watcher::Error::WatchError(e) => e.into(),
+            watcher::Error::NoResourceVersion => AppError::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<KubeconfigError> for AppError {
+    fn from(e: KubeconfigError) -> Self {
+        match e {
+            KubeconfigError::LoadContext(context) => {
+                AppError::ContextMissing(format!("Context {context} is not found in kubeconfig"))
+            }
+            KubeconfigError::CurrentContextNotSet => {
+                AppError::ContextMissing("Current context is not set in kubeconfig".to_string())
+            }
+            KubeconfigError::LoadClientCertificate(_) | KubeconfigError::LoadClientKey(_) => {
+                AppError::AuthFailed(e.to_string())
+            }
+            _ => AppError::KubeconfigMissing(e.to_string()),
+        }
+    }
+}
+
+impl From<CommandNotFound> for AppError {
+    fn from(e: CommandNotFound) -> Self {
+        AppError::EditorNotFound(e)
+    }
+}
@@ -0,0 +1,368 @@
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_shell::{process::CommandEvent, ShellExt};
+use tokio::time;
+
+use crate::error::AppError;
+use crate::settings::SettingsStore;
+use crate::terminal::SHELL_SCRIPT;
+use crate::watcher::ContainerState;
+use crate::{AppSettings, AppStatus};
+
+/// Time to wait for the terminal emulator to fail before assuming it is opened.
+///
+/// Some terminals exit as soon as the window is opened, others run until the window is closed.
+const STARTUP_TIMEOUT_MSEC: u64 = 1000;
+
+/// Terminal emulator to run `kubectl exec`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TerminalApp {
+    #[serde(rename = "gnome-terminal")]
+    GnomeTerminal,
+    Kitty,
+    Alacritty,
+    /// iTerm2 on macOS, driven by AppleScript
+    #[serde(rename = "iterm")]
+    ITerm,
+    /// Terminal.app on macOS, driven by AppleScript
+    Terminal,
+    /// Command line template by `custom_terminal`
+    Custom,
+}
+
+impl Default for TerminalApp {
+    /// Terminal installed by default on the platform
+    fn default() -> Self {
+        if cfg!(target_os = "macos") {
+            TerminalApp::Terminal
+        } else if cfg!(target_os = "windows") {
+            TerminalApp::Custom
+        } else {
+            TerminalApp::GnomeTerminal
+        }
+    }
+}
+
+/// Command line template of the custom terminal in settings.json.
+///
+/// Each argument can contain placeholders: `{context}`, `{namespace}`, `{pod}`, `{container}` and
+/// `{command}` (the `kubectl exec` command line quoted for the shell).
+/// The argument `{args}` is expanded to the arguments of `kubectl exec` one by one.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CustomTerminal {
+    pub command: String,
+    pub args: Vec<String>,
+}
+
+/// Container to open the shell
+pub struct ShellTarget<'a> {
+    pub context: &'a str,
+    pub namespace: &'a str,
+    pub pod_name: &'a str,
+    pub container_name: &'a str,
+    pub shell: &'a str,
+}
+
+impl ShellTarget<'_> {
+    /// `kubectl exec` to run the shell, falling back to `/bin/sh` same as the embedded terminal
+    fn kubectl_args(&self) -> Vec<String> {
+        [
+            "kubectl",
+            "exec",
+            "-it",
+            "--context",
+            self.context,
+            "-n",
+            self.namespace,
+            self.pod_name,
+            "-c",
+            self.container_name,
+            "--",
+            "sh",
+            "-c",
+            SHELL_SCRIPT,
+            self.shell,
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect()
+    }
+}
+
+/// Command line to open the shell of the container in the terminal emulator
+pub struct ExternalTerminal {
+    pub command: String,
+    args: Vec<String>,
+}
+
+impl ExternalTerminal {
+    pub fn new(app: TerminalApp, settings: &AppSettings) -> Result<Self, AppError> {
+        match app {
+            TerminalApp::Custom => settings
+                .custom_terminal
+                .as_ref()
+                .map(|custom_terminal| Self {
+                    command: custom_terminal.command.clone(),
+                    args: custom_terminal.args.clone(),
+                })
+                .ok_or(AppError::InvalidInput(
+                    "custom_terminal is not set in settings.json".to_string(),
+                )),
+            _ => Ok(Self::builtin(app)),
+        }
+    }
+
+    /// Command line of the terminal known to Hugill. `Custom` has no arguments.
+    fn builtin(app: TerminalApp) -> Self {
+        let (command, args): (&str, &[&str]) = match app {
+            TerminalApp::GnomeTerminal => ("gnome-terminal", &["--", "{args}"]),
+            TerminalApp::Kitty => ("kitty", &["{args}"]),
+            TerminalApp::Alacritty => ("alacritty", &["-e", "{args}"]),
+            TerminalApp::ITerm => (
+                "osascript",
+                &[
+                    "-e",
+                    r#"tell application "iTerm" to create window with default profile command "{command}""#,
+                ],
+            ),
+            TerminalApp::Terminal => (
+                "osascript",
+                &[
+                    "-e",
+                    r#"tell application "Terminal" to do script "{command}""#,
+                    "-e",
+                    r#"tell application "Terminal" to activate"#,
+                ],
+            ),
+            TerminalApp::Custom => ("", &[]),
+        };
+        Self {
+            command: command.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+
+    /// Arguments with placeholders replaced by the target.
+    ///
+    /// `{command}` of AppleScript is also escaped for the string literal of AppleScript.
+    pub fn args(&self, target: &ShellTarget) -> Vec<String> {
+        let kubectl_args = target.kubectl_args();
+        let command = kubectl_args
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<String>>()
+            .join(" ");
+        let command = if self.command == "osascript" {
+            command.replace('\\', "\\\\").replace('"', "\\\"")
+        } else {
+            command
+        };
+        self.args
+            .iter()
+            .flat_map(|arg| match arg.as_str() {
+                "{args}" => kubectl_args.clone(),
+                _ => vec![arg
+                    .replace("{context}", target.context)
+                    .replace("{namespace}", target.namespace)
+                    .replace("{pod}", target.pod_name)
+                    .replace("{container}", target.container_name)
+                    .replace("{command}", &command)],
+            })
+            .collect()
+    }
+}
+
+/// Quote the argument for POSIX shells unless it has no special characters
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@,+".contains(c))
+    {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r#"'\''"#))
+    }
+}
+
+/// Open the shell of the container by `kubectl exec` in the terminal emulator.
+///
+/// Returns when the terminal is opened, or fails to start.
+#[tauri::command]
+pub async fn open_shell(
+    app_handle: tauri::AppHandle,
+    context: String,
+    namespace: String,
+    pod_name: String,
+    container_name: String,
+) -> Result<(), AppError> {
+    let app_settings = app_handle
+        .state::<Mutex<SettingsStore>>()
+        .lock()
+        .unwrap()
+        .app_settings();
+    let external_terminal = ExternalTerminal::new(app_settings.terminal_app, &app_settings)?;
+    // kubectl fails and the window is closed at once if the container is not running
+    let state = app_handle
+        .state::<Mutex<AppStatus>>()
+        .lock()
+        .unwrap()
+        .cluster_statuses
+        .get(&context)
+        .and_then(|cluster| {
+            cluster
+                .pods
+                .iter()
+                .find(|pod| pod.namespace == namespace && pod.name == pod_name)
+                .and_then(|pod| {
+                    pod.containers
+                        .iter()
+                        .find(|container| container.name == container_name)
+                })
+                .map(|container| container.state.clone())
+        });
+    if let Some(state) = state.filter(|state| !matches!(state, ContainerState::Running { .. })) {
+        return Err(AppError::ContainerNotRunning(format!(
+            "{container_name} is not running ({})",
+            state.reason().unwrap_or("not started")
+        )));
+    }
+    let args = external_terminal.args(&ShellTarget {
+        context: &context,
+        namespace: &namespace,
+        pod_name: &pod_name,
+        container_name: &container_name,
+        shell: &app_settings.terminal_shell,
+    });
+    println!(
+        "Open shell of {pod_name} ({container_name}) in {}",
+        external_terminal.command
+    );
+    launch(&app_handle, &external_terminal.command, args).await
+}
+
+/// Run the terminal emulator and wait for it to fail to start
+async fn launch(handle: &AppHandle, command: &str, args: Vec<String>) -> Result<(), AppError> {
+    // ex: the terminal emulator is not installed
+    let (mut rx, _child) = handle
+        .shell()
+        .command(command)
+        .args(args)
+        .spawn()
+        .map_err(|e| AppError::TerminalFailed(format!("Failed to run {command}: {e}")))?;
+    let deadline = time::Instant::now() + time::Duration::from_millis(STARTUP_TIMEOUT_MSEC);
+    let mut stderr: Vec<String> = Vec::new();
+    let exit_code = loop {
+        match time::timeout_at(deadline, rx.recv()).await {
+            Ok(Some(CommandEvent::Stderr(line))) => {
+                stderr.push(String::from_utf8_lossy(&line).trim_end().to_string())
+            }
+            Ok(Some(CommandEvent::Error(e))) => stderr.push(e),
+            Ok(Some(CommandEvent::Terminated(payload))) => break payload.code,
+            Ok(Some(_)) => {}
+            Ok(None) => break None,
+            Err(_) => {
+                // Still running. Keep reading the output not to block the terminal emulator.
+                tauri::async_runtime::spawn(async move { while rx.recv().await.is_some() {} });
+                return Ok(());
+            }
+        }
+    };
+    match exit_code {
+        // Exited after opening the window
+        Some(0) => Ok(()),
+        code => {
+            println!("Exit with code: {code:?}");
+            let mut message = format!(
+                "{command} exited with code {}",
+                code.map_or("unknown".to_string(), |code| code.to_string())
+            );
+            if !stderr.is_empty() {
+                message = format!("{message}: {}", stderr.join("\n"));
+            }
+            Err(AppError::TerminalFailed(message))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: ShellTarget = ShellTarget {
+        context: "my context",
+        namespace: "default",
+        pod_name: "web-0",
+        container_name: r#"it's "app" \ 1"#,
+        shell: "bash",
+    };
+
+    /// Content of the AppleScript string literal, or `None` if it has an unescaped double quote
+    fn unescape_applescript(literal: &str) -> Option<String> {
+        let mut chars = literal.chars();
+        let mut unescaped = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => unescaped.push(chars.next()?),
+                '"' => return None,
+                _ => unescaped.push(c),
+            }
+        }
+        Some(unescaped)
+    }
+
+    #[test]
+    fn quote_for_shell() {
+        assert_eq!(shell_quote("web-0"), "web-0");
+        assert_eq!(shell_quote("my context"), "'my context'");
+        assert_eq!(shell_quote("it's"), r#"'it'\''s'"#);
+        assert_eq!(shell_quote(r#"a"b\c"#), r#"'a"b\c'"#);
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn expand_args_one_by_one() {
+        let args = ExternalTerminal::builtin(TerminalApp::GnomeTerminal).args(&TARGET);
+        assert_eq!(args[0], "--");
+        // Arguments are passed without the shell, so they are not quoted
+        assert_eq!(&args[1..], TARGET.kubectl_args().as_slice());
+        assert!(args.contains(&"my context".to_string()));
+        assert!(args.contains(&r#"it's "app" \ 1"#.to_string()));
+    }
+
+    #[test]
+    fn escape_command_for_applescript() {
+        let args = ExternalTerminal::builtin(TerminalApp::Terminal).args(&TARGET);
+        let script = args[1]
+            .strip_prefix(r#"tell application "Terminal" to do script ""#)
+            .and_then(|script| script.strip_suffix('"'))
+            .unwrap();
+        let command = unescape_applescript(script).unwrap();
+        assert!(command.starts_with("kubectl exec -it --context 'my context' -n default web-0 -c "));
+        assert!(command.contains(r#"'it'\''s "app" \ 1'"#));
+        assert_eq!(args[3], r#"tell application "Terminal" to activate"#);
+    }
+
+    #[test]
+    fn replace_placeholders_of_custom_terminal() {
+        let terminal = ExternalTerminal {
+            command: "wezterm".to_string(),
+            args: vec![
+                "start".to_string(),
+                "--class={context}/{container}".to_string(),
+                "--".to_string(),
+                "sh".to_string(),
+                "-c".to_string(),
+                "{command}".to_string(),
+            ],
+        };
+        let args = terminal.args(&TARGET);
+        assert_eq!(args[1], r#"--class=my context/it's "app" \ 1"#);
+        // Not escaped for AppleScript
+        assert!(args[5].contains(r#"'it'\''s "app" \ 1'"#));
+        assert!(args[5].starts_with("kubectl exec -it --context 'my context' "));
+    }
+}
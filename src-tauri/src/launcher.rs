@@ -0,0 +1,373 @@
+use std::env;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::AppSettings;
+
+/// Editor to open the remote container
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Editor {
+    /// Visual Studio Code launched by `code_command`
+    #[default]
+    Stable,
+    /// Visual Studio Code - Insiders
+    Insiders,
+    Cursor,
+    #[serde(rename = "vscodium")]
+    VSCodium,
+    /// Command line template by `custom_editor`
+    Custom,
+}
+
+/// Command line template of the custom editor in settings.json.
+///
+/// Each argument can contain placeholders: `{context}`, `{namespace}`, `{pod}`, `{container}`,
+/// `{path}`, `{authority}` (ex: `k8s-container+context=...`) and `{uri}` (ex: `vscode-remote://...`).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CustomEditor {
+    pub command: String,
+    pub args: Vec<String>,
+}
+
+/// How the editor opens the path in the container
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OpenMode {
+    /// Open the folder by `--folder-uri`
+    #[default]
+    Folder,
+    /// Open the multi-root workspace file (`.code-workspace`) by `--file-uri`
+    File,
+}
+
+impl OpenMode {
+    pub fn of(path: &str) -> Self {
+        if path.ends_with(".code-workspace") {
+            OpenMode::File
+        } else {
+            OpenMode::Folder
+        }
+    }
+}
+
+/// Path in the container to open
+pub struct RemoteTarget<'a> {
+    pub context: &'a str,
+    pub namespace: &'a str,
+    pub pod_name: &'a str,
+    pub container_name: &'a str,
+    pub path: &'a str,
+    pub open_mode: OpenMode,
+}
+
+impl RemoteTarget<'_> {
+    /// Authority of the remote URI handled by the Kubernetes extension
+    pub fn authority(&self) -> String {
+        let s = format!(
+            "k8s-container+context={}+podname={}+namespace={}+name={}",
+            self.context, self.pod_name, self.namespace, self.container_name
+        );
+        utf8_percent_encode(&s, NON_ALPHANUMERIC).to_string()
+    }
+}
+
+/// Command line to open the remote container in the editor
+pub struct Launcher {
+    pub command: String,
+    args: Vec<String>,
+}
+
+impl Launcher {
+    pub fn new(editor: Editor, settings: &AppSettings) -> Result<Self, AppError> {
+        match editor {
+            Editor::Stable => match &settings.code_command {
+                Some(code_command) => Ok(Self::vscode(code_command)),
+                None => Ok(Self::vscode(&discover_command(editor)?.to_string_lossy())),
+            },
+            Editor::Insiders | Editor::Cursor | Editor::VSCodium => {
+                Ok(Self::vscode(&discover_command(editor)?.to_string_lossy()))
+            }
+            Editor::Custom => settings
+                .custom_editor
+                .as_ref()
+                .map(|custom_editor| Self {
+                    command: custom_editor.command.clone(),
+                    args: custom_editor.args.clone(),
+                })
+                .ok_or(AppError::InvalidInput(
+                    "custom_editor is not set in settings.json".to_string(),
+                )),
+        }
+    }
+
+    /// Editors compatible with the command line of Visual Studio Code
+    fn vscode(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            args: vec!["--folder-uri".to_string(), "{uri}".to_string()],
+        }
+    }
+
+    /// Arguments with placeholders replaced by the target.
+    ///
+    /// `--folder-uri` is replaced with `--file-uri` to open the workspace file.
+    pub fn args(&self, target: &RemoteTarget) -> Vec<String> {
+        let authority = target.authority();
+        let uri = format!("vscode-remote://{authority}{}", target.path);
+        self.args
+            .iter()
+            .map(|arg| match (target.open_mode, arg.as_str()) {
+                (OpenMode::File, "--folder-uri") => "--file-uri".to_string(),
+                _ => arg
+                    .replace("{context}", target.context)
+                    .replace("{namespace}", target.namespace)
+                    .replace("{pod}", target.pod_name)
+                    .replace("{container}", target.container_name)
+                    .replace("{path}", target.path)
+                    .replace("{authority}", &authority)
+                    .replace("{uri}", &uri),
+            })
+            .collect()
+    }
+}
+
+/// Error when the command of the editor is not found in any candidate locations
+#[derive(Serialize, Clone, Debug)]
+pub struct CommandNotFound {
+    pub editor: Editor,
+    /// Name of the editor app (ex: "Cursor")
+    pub app: &'static str,
+    pub command: &'static str,
+    pub checked: Vec<PathBuf>,
+}
+
+impl fmt::Display for CommandNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // code_command is only for Visual Studio Code
+        let hint = match self.editor {
+            Editor::Stable | Editor::Custom => {
+                "Set code_command or custom_editor in settings.json."
+            }
+            _ => {
+                "Add it to PATH, or set custom_editor with the editor \"custom\" in settings.json."
+            }
+        };
+        write!(
+            f,
+            "{} command of {} not found. {hint} Checked locations: {}",
+            self.command,
+            self.app,
+            self.checked
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    }
+}
+
+impl std::error::Error for CommandNotFound {}
+
+/// Find the command of the editor from `PATH` and well-known install locations.
+///
+/// GUI apps on macOS are launched with the minimal `PATH`, so install locations are also checked.
+pub fn discover_command(editor: Editor) -> Result<PathBuf, CommandNotFound> {
+    let (command, app, install_locations) = candidates(editor);
+    let path_locations = env::var_os("PATH")
+        .map(|paths| {
+            env::split_paths(&paths)
+                .map(|dir| dir.join(executable_name(command)))
+                .collect::<Vec<PathBuf>>()
+        })
+        .unwrap_or_default();
+    let checked: Vec<PathBuf> = path_locations
+        .into_iter()
+        .chain(install_locations)
+        .collect();
+    checked
+        .iter()
+        .find(|path| is_executable(path))
+        .cloned()
+        .ok_or(CommandNotFound {
+            editor,
+            app,
+            command,
+            checked,
+        })
+}
+
+/// Name of the command, name of the app and install locations of the editor for each platform
+fn candidates(editor: Editor) -> (&'static str, &'static str, Vec<PathBuf>) {
+    let home = env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    let (command, mac_app, flatpak_id) = match editor {
+        Editor::Stable | Editor::Custom => ("code", "Visual Studio Code", "com.visualstudio.code"),
+        Editor::Insiders => (
+            "code-insiders",
+            "Visual Studio Code - Insiders",
+            "com.visualstudio.code.insiders",
+        ),
+        Editor::Cursor => ("cursor", "Cursor", "com.cursor.Cursor"),
+        Editor::VSCodium => ("codium", "VSCodium", "com.vscodium.codium"),
+    };
+    let mut locations: Vec<PathBuf> = Vec::new();
+    if cfg!(target_os = "macos") {
+        // The command in the app bundle of VS Code is always "code"
+        let bundle_command = match editor {
+            Editor::Cursor | Editor::VSCodium => command,
+            _ => "code",
+        };
+        for applications in [PathBuf::from("/Applications"), home.join("Applications")] {
+            locations.push(
+                applications
+                    .join(format!("{mac_app}.app"))
+                    .join("Contents/Resources/app/bin")
+                    .join(bundle_command),
+            );
+        }
+        locations.push(PathBuf::from("/usr/local/bin").join(command));
+        locations.push(PathBuf::from("/opt/homebrew/bin").join(command));
+    } else if cfg!(target_os = "windows") {
+        for program_files in [
+            env::var_os("LOCALAPPDATA").map(|dir| PathBuf::from(dir).join("Programs")),
+            env::var_os("ProgramFiles").map(PathBuf::from),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            let dir = match editor {
+                Editor::Stable | Editor::Custom => "Microsoft VS Code",
+                Editor::Insiders => "Microsoft VS Code Insiders",
+                Editor::Cursor => "cursor\\resources\\app",
+                Editor::VSCodium => "VSCodium",
+            };
+            locations.push(
+                program_files
+                    .join(dir)
+                    .join("bin")
+                    .join(executable_name(command)),
+            );
+        }
+    } else {
+        locations.push(PathBuf::from("/usr/bin").join(command));
+        locations.push(PathBuf::from("/usr/local/bin").join(command));
+        locations.push(PathBuf::from("/snap/bin").join(command));
+        locations.push(PathBuf::from("/var/lib/flatpak/exports/bin").join(flatpak_id));
+        locations.push(
+            home.join(".local/share/flatpak/exports/bin")
+                .join(flatpak_id),
+        );
+        locations.push(home.join(".local/bin").join(command));
+    }
+    (command, mac_app, locations)
+}
+
+fn executable_name(command: &str) -> String {
+    if cfg!(target_os = "windows") {
+        format!("{command}.cmd")
+    } else {
+        command.to_string()
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(path: &str) -> RemoteTarget<'_> {
+        RemoteTarget {
+            context: "my context",
+            namespace: "default",
+            pod_name: "web-0",
+            container_name: r#"it's "app" \ 1"#,
+            path,
+            open_mode: OpenMode::of(path),
+        }
+    }
+
+    #[test]
+    fn open_mode_of_path() {
+        assert_eq!(OpenMode::of("/work"), OpenMode::Folder);
+        assert_eq!(OpenMode::of("/work/app.code-workspace"), OpenMode::File);
+    }
+
+    #[test]
+    fn encode_authority() {
+        let authority = target("/work").authority();
+        assert!(authority.starts_with("k8s%2Dcontainer%2Bcontext%3Dmy%20context%2B"));
+        assert!(authority.ends_with("name%3Dit%27s%20%22app%22%20%5C%201"));
+        assert!(authority
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '%'));
+    }
+
+    #[test]
+    fn open_folder_uri() {
+        let target = target("/work");
+        let args = Launcher::vscode("code").args(&target);
+        assert_eq!(
+            args,
+            [
+                "--folder-uri".to_string(),
+                format!("vscode-remote://{}/work", target.authority())
+            ]
+        );
+    }
+
+    #[test]
+    fn open_file_uri_of_workspace_file() {
+        let target = target("/work/app.code-workspace");
+        let args = Launcher::vscode("code").args(&target);
+        assert_eq!(
+            args,
+            [
+                "--file-uri".to_string(),
+                format!(
+                    "vscode-remote://{}/work/app.code-workspace",
+                    target.authority()
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn replace_placeholders_of_custom_editor() {
+        let launcher = Launcher {
+            command: "my-editor".to_string(),
+            args: vec![
+                "--folder-uri".to_string(),
+                "{context}:{namespace}/{pod}/{container}".to_string(),
+                "--path={path}".to_string(),
+            ],
+        };
+        let args = launcher.args(&target("/work/app.code-workspace"));
+        assert_eq!(
+            args,
+            [
+                "--file-uri",
+                r#"my context:default/web-0/it's "app" \ 1"#,
+                "--path=/work/app.code-workspace",
+            ]
+        );
+    }
+}
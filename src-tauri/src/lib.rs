@@ -1,168 +1,574 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Mutex;
 
-use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use error::AppError;
+use external_terminal::{CustomTerminal, TerminalApp};
+use launcher::{CustomEditor, Editor, Launcher, OpenMode, RemoteTarget};
+use portforward::{ForwardTarget, PortForwardManager, PortForwardStatus};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use settings::SettingsStore;
+use settings::{GeneralSettings, Selection, SettingsStore};
+#[cfg(target_os = "macos")]
+use tauri::TitleBarStyle;
 use tauri::{
     async_runtime::JoinHandle,
     include_image,
-    menu::{IconMenuItem, Menu, MenuBuilder, MenuItem, NativeIcon},
+    menu::{IconMenuItem, Menu, MenuBuilder, MenuItem, NativeIcon, SubmenuBuilder},
     tray::{MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    AppHandle, Emitter, Error, Listener, Manager, TitleBarStyle, WebviewUrl, WebviewWindowBuilder,
-    Wry,
+    AppHandle, Emitter, Error, Listener, Manager, RunEvent, WebviewUrl, WebviewWindowBuilder, Wry,
 };
-use tauri_plugin_shell::ShellExt;
+use tauri_plugin_shell::{process::CommandEvent, ShellExt};
 use tauri_plugin_store::StoreExt;
-use watcher::{ClusterStatus, PodStatus};
+use tokio::sync::watch;
+use watcher::{
+    ClusterStatus, ContainerState, KubeContexts, PodStatus, WatchMode, WatcherError, Workload,
+};
+use workspace::{find_workspace, is_ignored_label_key, WorkspaceSetting, WorkspaceTarget};
 
+mod debug;
+mod error;
+mod external_terminal;
+mod launcher;
+mod logs;
+mod portforward;
+mod remote;
 mod settings;
+mod terminal;
 mod watcher;
-
-#[derive(Serialize, Deserialize, Clone, Debug)]
-struct WorkspaceSetting {
-    context: String,
-    namespace: String,
-    container_name: String,
-    workspace_folder: String,
-    labels: HashMap<String, String>,
-}
+mod workspace;
 
 pub struct AppSettings {
-    namespace: Option<String>,
+    contexts: Option<Selection>,
+    namespace: Option<Selection>,
+    watch_mode: WatchMode,
     poll_interval_msec: u64,
     workspaces: Vec<WorkspaceSetting>,
-    code_command: String,
+    /// Command of VS Code. Discovered from `PATH` and install locations if not set.
+    code_command: Option<String>,
+    editor: Editor,
+    custom_editor: Option<CustomEditor>,
+    /// Label keys not used to match workspaces
+    ignored_label_keys: Vec<String>,
+    /// Check that the workspace folder exists in the container before launching the editor
+    check_workspace_folder: bool,
+    /// Shell of the terminal, falling back to `/bin/sh` if not found in the container
+    terminal_shell: String,
+    /// Terminal emulator to open the shell by `kubectl exec`
+    terminal_app: TerminalApp,
+    custom_terminal: Option<CustomTerminal>,
+    /// Image of the ephemeral container to debug pods
+    debug_image: String,
 }
 
 struct AppStatus {
-    watcher_join_handle: Option<JoinHandle<()>>,
+    watcher_join_handles: Vec<JoinHandle<()>>,
+    /// Notify watchers to emit cluster statuses again after workspaces are changed
+    watcher_refresh: watch::Sender<()>,
     tray_opened: bool,
-    cluster_status: Option<ClusterStatus>,
+    /// Cluster status for each context
+    cluster_statuses: BTreeMap<String, ClusterStatus>,
+}
+
+/// Prefix of the identifier of the menu item to stop the port forward
+const STOP_PORT_FORWARD_MENU_ID_PREFIX: &str = "stop-port-forward:";
+
+/// Identifier of the menu item to open the pod from the system tray
+#[derive(Serialize, Deserialize, Clone)]
+struct PodMenuId {
+    context: String,
+    namespace: String,
+    pod_name: String,
+    container_name: String,
+    /// Folder to open. Use the workspace folder of the container if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workspace_folder: Option<String>,
+    /// Open the shell in the terminal emulator instead of the editor
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    open_shell: bool,
+}
+
+/// Progress of opening the container in the editor
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum LaunchStatus {
+    Launching,
+    Launched,
+    Failed,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct LaunchProgress {
+    context: String,
+    namespace: String,
+    pod_name: String,
+    container_name: String,
+    workspace_folder: String,
+    status: LaunchStatus,
+    /// Error message when failed, or warnings when launched
+    message: Option<String>,
+}
+
+/// Line of stdout or stderr of the editor command
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct LaunchOutput {
+    context: String,
+    namespace: String,
+    pod_name: String,
+    container_name: String,
+    /// "stdout" or "stderr"
+    stream: String,
+    line: String,
 }
 
 #[tauri::command]
-fn start_cluster_watcher(app_handle: tauri::AppHandle) -> Result<(), String> {
+fn start_cluster_watcher(app_handle: tauri::AppHandle) -> Result<(), AppError> {
     stop_cluster_watcher(app_handle.clone());
     let app_settings = app_handle
         .state::<Mutex<SettingsStore>>()
         .lock()
         .unwrap()
         .app_settings();
-    let poll_interval_msec = app_settings.poll_interval_msec;
-    let namespace = app_settings.namespace;
-    watcher::start(app_handle.clone(), namespace, poll_interval_msec).map_or_else(
-        |e| {
-            println!("Failed to start watcher: {e}");
-            Err(format!("Failed to setup containers watcher: {e}"))
-        },
-        |join_handle| {
+    let contexts = watcher::resolve_contexts(app_settings.contexts.as_ref()).inspect_err(|e| {
+        println!("Failed to resolve contexts: {e}");
+    })?;
+    for context in contexts {
+        match watcher::start(
+            app_handle.clone(),
+            context.clone(),
+            app_settings.namespace.clone(),
+            app_settings.watch_mode,
+            app_settings.poll_interval_msec,
             app_handle
                 .state::<Mutex<AppStatus>>()
                 .lock()
                 .unwrap()
-                .watcher_join_handle = Some(join_handle);
-            println!("Watcher started");
-            Ok(())
-        },
-    )
+                .watcher_refresh
+                .subscribe(),
+        ) {
+            Ok(join_handle) => {
+                app_handle
+                    .state::<Mutex<AppStatus>>()
+                    .lock()
+                    .unwrap()
+                    .watcher_join_handles
+                    .push(join_handle);
+                println!("Watcher started: {context}");
+            }
+            Err(e) => {
+                // Keep watching other contexts
+                println!("Failed to start watcher: {e}");
+                app_handle
+                    .emit_to(
+                        "hugill",
+                        "cluster-status-error",
+                        WatcherError {
+                            context,
+                            code: e.code().to_string(),
+                            message: format!("Failed to setup containers watcher: {e}"),
+                        },
+                    )
+                    .expect("failed to emit watcher error event");
+            }
+        }
+    }
+    Ok(())
 }
 
 #[tauri::command]
 fn stop_cluster_watcher(app_handle: tauri::AppHandle) {
     let state = app_handle.state::<Mutex<AppStatus>>();
     let mut state = state.lock().unwrap();
-    if let Some(watcher_join_handle) = &state.watcher_join_handle {
+    for watcher_join_handle in state.watcher_join_handles.drain(..) {
         watcher_join_handle.abort();
-        state.watcher_join_handle = None;
     }
+    state.cluster_statuses.clear();
 }
 
+/// Open the container in the editor.
+///
+/// Progress is emitted as "launch-progress" and the output of the editor command as "launch-output".
 #[tauri::command]
-fn open_remote_container(
+#[allow(clippy::too_many_arguments)]
+async fn open_remote_container(
     app_handle: tauri::AppHandle,
-    context: &str,
-    namespace: &str,
-    pod_name: &str,
-    container_name: &str,
-    labels: HashMap<String, String>,
-    workspace_folder: &str,
-) -> Result<(), String> {
-    let s = format!("k8s-container+context={context}+podname={pod_name}+namespace={namespace}+name={container_name}");
-    let encoded = utf8_percent_encode(&s, NON_ALPHANUMERIC).to_string();
-    let remote_uri = format!("vscode-remote://{encoded}{workspace_folder}");
-    let code_command = app_handle
-        .state::<Mutex<SettingsStore>>()
+    context: String,
+    namespace: String,
+    pod_name: String,
+    container_name: Option<String>,
+    labels: BTreeMap<String, String>,
+    workspace_folder: String,
+    editor: Option<Editor>,
+    selector_keys: Option<Vec<String>>,
+    match_workload: Option<bool>,
+) -> Result<(), AppError> {
+    // The workspace without the workload nor labels matches every pod of the container name
+    if match_workload == Some(false) && selector_keys.as_ref().is_some_and(Vec::is_empty) {
+        return Err(AppError::InvalidInput(
+            "Select at least one label to match the workspace".to_string(),
+        ));
+    }
+    let pod = app_handle
+        .state::<Mutex<AppStatus>>()
         .lock()
         .unwrap()
-        .app_settings()
-        .code_command;
-    let shell = app_handle.shell();
-    let output = tauri::async_runtime::block_on(async move {
-        shell
-            .command(code_command)
-            .args(["--folder-uri", &remote_uri])
-            .output()
-            .await
-    });
-    match output {
+        .cluster_statuses
+        .get(&context)
+        .and_then(|cluster| {
+            cluster
+                .pods
+                .iter()
+                .find(|pod| pod.namespace == namespace && pod.name == pod_name)
+                .cloned()
+        });
+    // Open the default container of the pod unless the container is specified
+    let container_name = container_name
+        .or_else(|| pod.as_ref().and_then(|pod| pod.default_container.clone()))
+        .ok_or(AppError::NotFound(format!(
+            "No container to open in {pod_name}"
+        )))?;
+    let progress = LaunchProgress {
+        context: context.clone(),
+        namespace: namespace.clone(),
+        pod_name: pod_name.clone(),
+        container_name: container_name.clone(),
+        workspace_folder: workspace_folder.clone(),
+        status: LaunchStatus::Launching,
+        message: None,
+    };
+    emit_launch_progress(&app_handle, progress.clone());
+    let result = launch_editor(&app_handle, &progress, pod.as_ref(), editor, &labels).await;
+    let workload = pod.and_then(|pod| pod.workload);
+    match &result {
+        Ok(skipped) => {
+            let workspace = save_workspace(
+                &app_handle,
+                &progress,
+                labels.clone(),
+                workload.clone(),
+                editor,
+                selector_keys,
+                match_workload,
+            );
+            let forward_errors = if workspace.auto_start_port_forwards {
+                let target = ForwardTarget {
+                    context: &progress.context,
+                    namespace: &progress.namespace,
+                    pod_name: &progress.pod_name,
+                    container_name: &progress.container_name,
+                    labels: &labels,
+                    workload: workload.as_ref(),
+                };
+                portforward::start_workspace(&app_handle, &target, &workspace).await
+            } else {
+                Vec::new()
+            };
+            // The editor is opened even if the folder is not checked or some ports are not forwarded
+            let warnings: Vec<String> = skipped
+                .iter()
+                .cloned()
+                .chain(forward_errors.iter().map(|e| e.to_string()))
+                .collect();
+            let message = (!warnings.is_empty()).then(|| warnings.join("\n"));
+            emit_launch_progress(
+                &app_handle,
+                LaunchProgress {
+                    status: LaunchStatus::Launched,
+                    message,
+                    ..progress
+                },
+            );
+        }
         Err(e) => {
-            // ex: code command not found
             println!("Failed to open remote container: {e}");
-            Err(format!("Failed to open remote container: {e}"))
+            emit_launch_progress(
+                &app_handle,
+                LaunchProgress {
+                    status: LaunchStatus::Failed,
+                    message: Some(e.to_string()),
+                    ..progress
+                },
+            );
         }
-        Ok(output) => {
-            if output.status.success() {
-                let settings_store = app_handle.state::<Mutex<SettingsStore>>();
-                let settings_store = settings_store.lock().unwrap();
-                let mut workspaces = settings_store.app_settings().workspaces;
-                let index = workspaces.iter().position(|ws| {
-                    if ws.context == context
-                        && ws.namespace == namespace
-                        && ws.container_name == container_name
-                    {
-                        let satisfied = ws
-                            .labels
-                            .iter()
-                            .all(|(k, v)| labels.get(k).map(|val| val == v).unwrap_or(false));
-                        if satisfied {
-                            return true;
-                        }
-                    }
-                    false
-                });
-                match index {
-                    Some(i) => {
-                        let ws = &mut workspaces[i];
-                        ws.workspace_folder = workspace_folder.to_string();
-                    }
-                    None => {
-                        let mut filtered_labels: HashMap<String, String> = HashMap::new();
-                        for (key, value) in labels.iter() {
-                            // ignore hash-related labels (e.g. "pod-template-hash")
-                            if !key.ends_with("-hash") {
-                                filtered_labels.insert(key.to_string(), value.to_string());
-                            }
-                        }
-                        workspaces.push(WorkspaceSetting {
-                            context: context.to_string(),
-                            namespace: namespace.to_string(),
-                            container_name: container_name.to_string(),
-                            workspace_folder: workspace_folder.to_string(),
-                            labels: filtered_labels,
-                        });
-                        println!("Added workspace folder for {container_name}");
-                    }
-                }
-                settings_store.update_workspaces(workspaces);
-                Ok(())
-            } else {
-                println!("Exit with code: {}", output.status.code().unwrap());
-                Err("Failed to open remote container".to_string())
+    }
+    result.map(|_| ())
+}
+
+/// Check the workspace folder and run the editor command until it exits.
+///
+/// Returns the reason if the check of the workspace folder is skipped.
+async fn launch_editor(
+    app_handle: &AppHandle,
+    target: &LaunchProgress,
+    pod: Option<&PodStatus>,
+    editor: Option<Editor>,
+    labels: &BTreeMap<String, String>,
+) -> Result<Option<String>, AppError> {
+    let context = target.context.as_str();
+    let namespace = target.namespace.as_str();
+    let pod_name = target.pod_name.as_str();
+    let container_name = target.container_name.as_str();
+    let workspace_folder = target.workspace_folder.as_str();
+    let app_settings = app_handle
+        .state::<Mutex<SettingsStore>>()
+        .lock()
+        .unwrap()
+        .app_settings();
+    // Prefer the editor chosen now, then the editor of the workspace, then the global one
+    let workspace_target = WorkspaceTarget {
+        context,
+        namespace,
+        container_name,
+        labels,
+        workload: pod.and_then(|pod| pod.workload.as_ref()),
+    };
+    let workspace = find_workspace(&app_settings.workspaces, &workspace_target).map(|(_, ws)| ws);
+    let launcher = Launcher::new(
+        editor
+            .or_else(|| workspace.and_then(|ws| ws.editor))
+            .unwrap_or(app_settings.editor),
+        &app_settings,
+    )?;
+    // Keep the mode saved in settings.json for the folder opened last time
+    let open_mode = workspace
+        .filter(|ws| ws.workspace_folder == workspace_folder)
+        .map_or_else(|| OpenMode::of(workspace_folder), |ws| ws.open_mode);
+    let mut skipped = None;
+    if app_settings.check_workspace_folder {
+        let state = pod
+            .iter()
+            .flat_map(|pod| pod.containers.iter())
+            .find(|container| container.name == container_name)
+            .map(|container| &container.state);
+        if let Some(state) = state.filter(|state| !matches!(state, ContainerState::Running { .. }))
+        {
+            return Err(AppError::ContainerNotRunning(format!(
+                "{container_name} is not running ({})",
+                state.reason().unwrap_or("not started")
+            )));
+        }
+        skipped = remote::check_path(
+            context,
+            namespace,
+            pod_name,
+            container_name,
+            workspace_folder,
+            open_mode,
+        )
+        .await?;
+    }
+    let args = launcher.args(&RemoteTarget {
+        context,
+        namespace,
+        pod_name,
+        container_name,
+        path: workspace_folder,
+        open_mode,
+    });
+    // ex: code command not found
+    let (mut rx, _child) = app_handle
+        .shell()
+        .command(&launcher.command)
+        .args(args)
+        .spawn()
+        .map_err(|e| AppError::EditorFailed(format!("Failed to open remote container: {e}")))?;
+    let mut exit_code = None;
+    while let Some(event) = rx.recv().await {
+        match event {
+            CommandEvent::Stdout(line) => emit_launch_output(app_handle, target, "stdout", &line),
+            CommandEvent::Stderr(line) => emit_launch_output(app_handle, target, "stderr", &line),
+            CommandEvent::Error(e) => {
+                emit_launch_output(app_handle, target, "stderr", e.as_bytes())
+            }
+            CommandEvent::Terminated(payload) => exit_code = payload.code,
+            _ => {}
+        }
+    }
+    match exit_code {
+        Some(0) => Ok(skipped),
+        code => {
+            println!("Exit with code: {code:?}");
+            Err(AppError::EditorFailed(format!(
+                "{} exited with code {}",
+                launcher.command,
+                code.map_or("unknown".to_string(), |code| code.to_string())
+            )))
+        }
+    }
+}
+
+/// Save the workspace folder opened successfully and return the saved workspace
+fn save_workspace(
+    app_handle: &AppHandle,
+    target: &LaunchProgress,
+    labels: BTreeMap<String, String>,
+    workload: Option<Workload>,
+    editor: Option<Editor>,
+    selector_keys: Option<Vec<String>>,
+    match_workload: Option<bool>,
+) -> WorkspaceSetting {
+    let workspace_folder = target.workspace_folder.as_str();
+    let settings_store = app_handle.state::<Mutex<SettingsStore>>();
+    let settings_store = settings_store.lock().unwrap();
+    let app_settings = settings_store.app_settings();
+    let mut workspaces = app_settings.workspaces;
+    let workspace_target = WorkspaceTarget {
+        context: &target.context,
+        namespace: &target.namespace,
+        container_name: &target.container_name,
+        labels: &labels,
+        workload: workload.as_ref(),
+    };
+    let index = find_workspace(&workspaces, &workspace_target).map(|(i, _)| i);
+    // Key the workspace on the workload which owns the pod unless the user opts out
+    let workload = workload.filter(|_| match_workload.unwrap_or(true));
+    // Labels chosen by the user, or all labels except volatile ones
+    let selected_labels = |selector_keys: &[String]| {
+        labels
+            .iter()
+            .filter(|(key, _)| selector_keys.contains(key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<HashMap<String, String>>()
+    };
+    let index = match index {
+        Some(i) => {
+            let ws = &mut workspaces[i];
+            ws.use_folder(workspace_folder);
+            if editor.is_some() {
+                ws.editor = editor;
             }
+            if let Some(selector_keys) = &selector_keys {
+                ws.labels = selected_labels(selector_keys);
+            }
+            if match_workload.is_some() {
+                ws.workload = workload;
+            }
+            i
+        }
+        None => {
+            let labels = match &selector_keys {
+                Some(selector_keys) => selected_labels(selector_keys),
+                // Labels are not needed to find the pods of the workload
+                None if workload.is_some() => HashMap::new(),
+                None => labels
+                    .iter()
+                    .filter(|(key, _)| !is_ignored_label_key(key, &app_settings.ignored_label_keys))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+            };
+            workspaces.push(WorkspaceSetting {
+                context: target.context.clone(),
+                namespace: target.namespace.clone(),
+                container_name: target.container_name.clone(),
+                workspace_folder: workspace_folder.to_string(),
+                open_mode: OpenMode::of(workspace_folder),
+                recent_folders: vec![workspace_folder.to_string()],
+                labels,
+                match_expressions: Vec::new(),
+                workload,
+                editor,
+                port_forwards: Vec::new(),
+                auto_start_port_forwards: false,
+            });
+            println!("Added workspace folder for {}", target.container_name);
+            workspaces.len() - 1
+        }
+    };
+    let workspace = workspaces[index].clone();
+    settings_store.update_workspaces(workspaces);
+    refresh_cluster_statuses(app_handle);
+    workspace
+}
+
+fn emit_launch_progress(handle: &AppHandle, progress: LaunchProgress) {
+    handle
+        .emit_to("hugill", "launch-progress", progress)
+        .expect("failed to emit launch progress");
+}
+
+fn emit_launch_output(handle: &AppHandle, target: &LaunchProgress, stream: &str, line: &[u8]) {
+    let output = LaunchOutput {
+        context: target.context.clone(),
+        namespace: target.namespace.clone(),
+        pod_name: target.pod_name.clone(),
+        container_name: target.container_name.clone(),
+        stream: stream.to_string(),
+        line: String::from_utf8_lossy(line).trim_end().to_string(),
+    };
+    handle
+        .emit_to("hugill", "launch-output", output)
+        .expect("failed to emit launch output");
+}
+
+/// Command to launch the editor, or the error with locations checked
+#[tauri::command]
+fn find_editor_command(
+    app_handle: tauri::AppHandle,
+    editor: Option<Editor>,
+) -> Result<String, AppError> {
+    let app_settings = app_handle
+        .state::<Mutex<SettingsStore>>()
+        .lock()
+        .unwrap()
+        .app_settings();
+    Launcher::new(editor.unwrap_or(app_settings.editor), &app_settings)
+        .map(|launcher| launcher.command)
+}
+
+#[tauri::command]
+fn get_kube_contexts() -> Result<KubeContexts, AppError> {
+    watcher::kube_contexts()
+}
+
+#[tauri::command]
+fn get_general_settings(app_handle: tauri::AppHandle) -> GeneralSettings {
+    app_handle
+        .state::<Mutex<SettingsStore>>()
+        .lock()
+        .unwrap()
+        .app_settings()
+        .into()
+}
+
+/// Validate and save settings, then restart the cluster watcher if needed.
+///
+/// Returns whether the cluster watcher is restarted.
+#[tauri::command]
+fn update_general_settings(
+    app_handle: tauri::AppHandle,
+    settings: GeneralSettings,
+) -> Result<bool, AppError> {
+    let kube_contexts = watcher::kube_contexts()
+        .map(|kube_contexts| kube_contexts.contexts)
+        .unwrap_or_default();
+    settings
+        .validate(&kube_contexts)
+        .map_err(AppError::InvalidInput)?;
+    let settings_store = app_handle.state::<Mutex<SettingsStore>>();
+    let current: GeneralSettings = settings_store.lock().unwrap().app_settings().into();
+    let restart = settings.requires_restart_watcher(&current);
+    // Resolve contexts before saving not to fail to watch on the next launch
+    if restart {
+        watcher::resolve_contexts(settings.contexts.as_ref())?;
+    }
+    settings_store
+        .lock()
+        .unwrap()
+        .update_general_settings(&settings);
+    if restart {
+        if let Err(e) = start_cluster_watcher(app_handle.clone()) {
+            // ex: kubeconfig is changed meanwhile
+            settings_store
+                .lock()
+                .unwrap()
+                .update_general_settings(&current);
+            let _ = start_cluster_watcher(app_handle.clone());
+            return Err(e);
         }
+        update_tray_menu(&app_handle);
     }
+    Ok(restart)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -173,7 +579,29 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             start_cluster_watcher,
             stop_cluster_watcher,
-            open_remote_container
+            open_remote_container,
+            find_editor_command,
+            get_kube_contexts,
+            get_general_settings,
+            update_general_settings,
+            workspace::list_workspaces,
+            workspace::update_workspace,
+            workspace::delete_workspace,
+            workspace::duplicate_workspace,
+            workspace::test_workspace,
+            remote::list_remote_directory,
+            remote::suggest_workspace_folders,
+            portforward::list_port_forwards,
+            portforward::start_port_forward,
+            portforward::stop_port_forward,
+            logs::start_log_stream,
+            logs::stop_log_stream,
+            terminal::open_terminal,
+            terminal::write_terminal,
+            terminal::resize_terminal,
+            terminal::close_terminal,
+            external_terminal::open_shell,
+            debug::create_debug_container
         ])
         .setup(|app| {
             let default_workspace_settings: [WorkspaceSetting; 0] = [];
@@ -182,12 +610,21 @@ pub fn run() {
                 .default("workspaces", json!(default_workspace_settings))
                 .build()?
                 .into();
+            let app_settings = store.app_settings();
+            match Launcher::new(app_settings.editor, &app_settings) {
+                Ok(launcher) => println!("Editor command: {}", launcher.command),
+                Err(e) => println!("{e}"),
+            }
             app.manage(Mutex::new(store));
             app.manage(Mutex::new(AppStatus {
-                watcher_join_handle: None,
+                watcher_join_handles: Vec::new(),
+                watcher_refresh: watch::Sender::new(()),
                 tray_opened: false,
-                cluster_status: None,
+                cluster_statuses: BTreeMap::new(),
             }));
+            app.manage(Mutex::new(PortForwardManager::default()));
+            app.manage(Mutex::new(logs::LogStreams::default()));
+            app.manage(Mutex::new(terminal::TerminalSessions::default()));
             let win_builder = WebviewWindowBuilder::new(app, "main", WebviewUrl::default())
                 .title("Hugill")
                 .inner_size(1024.0, 768.0)
@@ -202,43 +639,88 @@ pub fn run() {
                 .tooltip("Hugill")
                 .icon(include_image!("./icons/SystemTray@2x.png"))
                 .icon_as_template(true)
-                .menu(&get_tray_menu(app.handle(), None)?)
+                .menu(&get_tray_menu(app.handle(), &[])?)
                 .on_menu_event(move |app, event| match event.id.as_ref() {
                     "quit" => {
                         app.exit(0);
                     }
-                    pod_id => {
+                    menu_id if menu_id.starts_with(STOP_PORT_FORWARD_MENU_ID_PREFIX) => {
+                        let Ok(id) = menu_id[STOP_PORT_FORWARD_MENU_ID_PREFIX.len()..].parse()
+                        else {
+                            return;
+                        };
+                        if let Err(e) = portforward::stop(app, id) {
+                            println!("Failed to stop port forward: {e}");
+                        }
+                    }
+                    menu_id => {
+                        let Ok(menu_id) = serde_json::from_str::<PodMenuId>(menu_id) else {
+                            return;
+                        };
+                        if menu_id.open_shell {
+                            let handle = handle.clone();
+                            tauri::async_runtime::spawn(async move {
+                                if let Err(e) = external_terminal::open_shell(
+                                    handle,
+                                    menu_id.context,
+                                    menu_id.namespace,
+                                    menu_id.pod_name,
+                                    menu_id.container_name,
+                                )
+                                .await
+                                {
+                                    println!("Failed to open shell: {e}");
+                                }
+                            });
+                            return;
+                        }
                         let app_state = app.state::<Mutex<AppStatus>>();
-                        let cluster = app_state
+                        let Some(cluster) = app_state
                             .lock()
                             .unwrap()
-                            .cluster_status
-                            .clone()
-                            .expect("cluster status not available");
-                        cluster
+                            .cluster_statuses
+                            .get(&menu_id.context)
+                            .cloned()
+                        else {
+                            // The watcher of the context is stopped after the menu is built
+                            println!("Cluster status not available: {}", menu_id.context);
+                            return;
+                        };
+                        let Some((pod, workspace_folder)) = cluster
                             .pods
-                            .iter()
-                            .find(|pod| pod.name == pod_id)
+                            .into_iter()
+                            .find(|pod| {
+                                pod.namespace == menu_id.namespace && pod.name == menu_id.pod_name
+                            })
                             .and_then(|pod| {
-                                pod.workspace_folder.clone().and_then(|workspace_folder| {
-                                    pod.container_name.clone().and_then(|container_name| {
-                                        let mut labels = HashMap::new();
-                                        for (key, value) in pod.labels.iter() {
-                                            labels.insert(key.clone(), value.clone());
-                                        }
-                                        open_remote_container(
-                                            handle.clone(),
-                                            &cluster.context,
-                                            &cluster.namespace,
-                                            &pod.name,
-                                            &container_name,
-                                            labels,
-                                            &workspace_folder,
-                                        )
-                                        .ok()
-                                    })
-                                })
-                            });
+                                let workspace_folder = pod
+                                    .containers
+                                    .iter()
+                                    .find(|container| container.name == menu_id.container_name)
+                                    .and_then(|container| {
+                                        menu_id
+                                            .workspace_folder
+                                            .clone()
+                                            .or_else(|| container.workspace_folder.clone())
+                                    })?;
+                                Some((pod, workspace_folder))
+                            })
+                        else {
+                            return;
+                        };
+                        // Launch in background not to block the tray menu
+                        tauri::async_runtime::spawn(open_remote_container(
+                            handle.clone(),
+                            cluster.context,
+                            pod.namespace,
+                            pod.name,
+                            Some(menu_id.container_name),
+                            pod.labels,
+                            workspace_folder,
+                            None,
+                            None,
+                            None,
+                        ));
                     }
                 })
                 .on_tray_icon_event(|tray, event| match event {
@@ -277,29 +759,21 @@ pub fn run() {
                 .build(app);
             let handle = app.handle().clone();
             let _ = app.listen("watcher", move |event| {
-                let status: ClusterStatus = serde_json::from_str(event.payload()).unwrap();
-                // Filter pods which has no workspace_folder setting
-                let pods: Vec<PodStatus> = status
-                    .clone()
-                    .pods
-                    .into_iter()
-                    .filter(|pod| pod.workspace_folder.is_some())
-                    .collect();
+                let status: ClusterStatus = match serde_json::from_str(event.payload()) {
+                    Ok(status) => status,
+                    Err(e) => {
+                        println!("Failed to parse watcher event: {e}");
+                        return;
+                    }
+                };
                 handle
                     .state::<Mutex<AppStatus>>()
                     .lock()
                     .unwrap()
-                    .cluster_status = Some(status.clone());
-                if let Some(tray) = handle.tray_by_id("hugill-tray") {
-                    if !handle
-                        .state::<Mutex<AppStatus>>()
-                        .lock()
-                        .unwrap()
-                        .tray_opened
-                    {
-                        let _ = tray.set_menu(get_tray_menu(&handle, Some(pods)).ok());
-                    }
-                }
+                    .cluster_statuses
+                    .insert(status.context.clone(), status.clone());
+                portforward::follow_pods(&handle, &status);
+                update_tray_menu(&handle);
                 handle
                     .emit_to("hugill", "cluster-status", status.clone())
                     .expect("failed to emit updated status");
@@ -308,59 +782,226 @@ pub fn run() {
             let handle = app.handle().clone();
             let _ = app.listen("watcher-error", move |event| {
                 // failed to receive cluster status
-                let message: String = serde_json::from_str(event.payload()).unwrap();
+                let error: WatcherError = match serde_json::from_str(event.payload()) {
+                    Ok(error) => error,
+                    Err(e) => {
+                        println!("Failed to parse watcher error event: {e}");
+                        return;
+                    }
+                };
+                // Keep the last pods since the watcher resumes without listing pods after temporary errors
+                let status = handle
+                    .state::<Mutex<AppStatus>>()
+                    .lock()
+                    .unwrap()
+                    .cluster_statuses
+                    .get_mut(&error.context)
+                    .map(|status| {
+                        status.error = Some(error.message.clone());
+                        status.clone()
+                    });
+                if let Some(status) = status {
+                    handle
+                        .emit_to("hugill", "cluster-status", status)
+                        .expect("failed to emit updated status");
+                }
                 handle
-                    .emit_to("hugill", "cluster-status-error", message)
+                    .emit_to("hugill", "cluster-status-error", error)
                     .expect("failed to emit watcher error event");
                 println!("watcher error event received: {}", event.payload());
-                if let Some(tray) = handle.tray_by_id("hugill-tray") {
-                    if !handle
-                        .state::<Mutex<AppStatus>>()
-                        .lock()
-                        .unwrap()
-                        .tray_opened
-                    {
-                        let _ = tray.set_menu(get_tray_menu(&handle, None).ok());
-                    }
-                }
             });
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            if let RunEvent::Exit = event {
+                // Close listening ports and connections to pods
+                portforward::stop_all(app_handle);
+            }
+        });
+}
+
+/// Emit cluster statuses again to apply changes of workspace settings
+fn refresh_cluster_statuses(handle: &AppHandle) {
+    handle
+        .state::<Mutex<AppStatus>>()
+        .lock()
+        .unwrap()
+        .watcher_refresh
+        .send_replace(());
+}
+
+/// Rebuild the menu of the system tray from the latest cluster statuses unless the user is opening it
+fn update_tray_menu(handle: &AppHandle) {
+    let clusters: Vec<ClusterStatus> = {
+        let state = handle.state::<Mutex<AppStatus>>();
+        let state = state.lock().unwrap();
+        if state.tray_opened {
+            return;
+        }
+        state.cluster_statuses.values().cloned().collect()
+    };
+    if let Some(tray) = handle.tray_by_id("hugill-tray") {
+        let _ = tray.set_menu(get_tray_menu(handle, &clusters).ok());
+    }
 }
 
-fn get_tray_menu(handle: &AppHandle, pods: Option<Vec<PodStatus>>) -> Result<Menu<Wry>, Error> {
-    let builder = MenuBuilder::new(handle);
-    match pods {
-        Some(pods) => {
-            let mut builder = builder;
-            for pod in pods {
-                let native_icon = match pod.status.as_str() {
-                    "Running" => NativeIcon::StatusAvailable,
-                    "Waiting" => NativeIcon::StatusPartiallyAvailable,
-                    "Terminated" => NativeIcon::StatusUnavailable,
-                    _ => NativeIcon::StatusNone,
+fn get_tray_menu(handle: &AppHandle, clusters: &[ClusterStatus]) -> Result<Menu<Wry>, Error> {
+    // Filter containers which has no workspace_folder setting
+    let clusters: Vec<&ClusterStatus> = clusters
+        .iter()
+        .filter(|cluster| {
+            cluster.pods.iter().any(|pod| {
+                pod.containers
+                    .iter()
+                    .any(|container| container.workspace_folder.is_some())
+            })
+        })
+        .collect();
+    let mut builder = MenuBuilder::new(handle);
+    // Identifier and text of the menu items to open the shell of running containers
+    let mut shell_items: Vec<(String, String)> = Vec::new();
+    for cluster in clusters.iter() {
+        // Show context names only if there are pods in multiple contexts
+        if clusters.len() > 1 {
+            builder = builder.item(&MenuItem::new(
+                handle,
+                &cluster.context,
+                false,
+                None::<&str>,
+            )?);
+        }
+        for pod in cluster.pods.iter() {
+            for container in pod.containers.iter() {
+                if container.workspace_folder.is_none() {
+                    continue;
+                }
+                let native_icon = if pod.terminating || container.failed {
+                    NativeIcon::StatusUnavailable
+                } else {
+                    match container.state {
+                        ContainerState::Running { .. } if container.ready => {
+                            NativeIcon::StatusAvailable
+                        }
+                        ContainerState::Running { .. } | ContainerState::Waiting { .. } => {
+                            NativeIcon::StatusPartiallyAvailable
+                        }
+                        // Completed successfully
+                        ContainerState::Terminated { .. } | ContainerState::Unknown => {
+                            NativeIcon::StatusNone
+                        }
+                    }
+                };
+                let menu_id = PodMenuId {
+                    context: cluster.context.clone(),
+                    namespace: pod.namespace.clone(),
+                    pod_name: pod.name.clone(),
+                    container_name: container.name.clone(),
+                    workspace_folder: None,
+                    open_shell: false,
                 };
+                // Prefix namespace to distinguish pods in multiple namespaces
+                let mut text = match &cluster.namespaces {
+                    Some(namespaces) if namespaces.len() == 1 => pod.name.clone(),
+                    _ => format!("{}/{}", pod.namespace, pod.name),
+                };
+                if pod.containers.len() > 1 {
+                    text = format!("{text} ({})", container.name);
+                }
+                if pod.terminating {
+                    text = format!("{text} - Terminating");
+                } else if let Some(reason) = container.state.reason() {
+                    text = format!("{text} - {reason}");
+                }
+                // Open the shell from the separate submenu not to add a click to open the editor
+                if matches!(container.state, ContainerState::Running { .. }) && !pod.terminating {
+                    let menu_id = PodMenuId {
+                        open_shell: true,
+                        ..menu_id.clone()
+                    };
+                    shell_items.push((serde_json::to_string(&menu_id)?, text.clone()));
+                }
+                // Choose one of the recent folders from the submenu
+                if container.recent_folders.len() > 1 {
+                    let mut submenu = SubmenuBuilder::new(handle, text);
+                    for folder in container.recent_folders.iter() {
+                        let menu_id = PodMenuId {
+                            workspace_folder: Some(folder.clone()),
+                            ..menu_id.clone()
+                        };
+                        submenu = submenu.item(&IconMenuItem::with_id_and_native_icon(
+                            handle,
+                            serde_json::to_string(&menu_id)?,
+                            folder,
+                            true,
+                            Some(native_icon),
+                            None::<&str>,
+                        )?);
+                    }
+                    builder = builder.item(&submenu.build()?);
+                    continue;
+                }
                 builder = builder.item(&IconMenuItem::with_id_and_native_icon(
                     handle,
-                    &pod.name,
-                    &pod.name,
+                    serde_json::to_string(&menu_id)?,
+                    text,
                     true,
                     Some(native_icon),
                     None::<&str>,
                 )?);
             }
-            builder.separator()
         }
-        None => builder,
+        builder = builder.separator();
+    }
+    if !shell_items.is_empty() {
+        let mut submenu = SubmenuBuilder::new(handle, "Open shell");
+        for (menu_id, text) in shell_items {
+            submenu = submenu.item(&MenuItem::with_id(
+                handle,
+                menu_id,
+                text,
+                true,
+                None::<&str>,
+            )?);
+        }
+        builder = builder.item(&submenu.build()?).separator();
+    }
+    let forwards = handle
+        .state::<Mutex<PortForwardManager>>()
+        .lock()
+        .unwrap()
+        .list();
+    for forward in forwards.iter() {
+        let mut text = format!(
+            "localhost:{} → {}:{}",
+            forward.local_port, forward.pod_name, forward.remote_port
+        );
+        if forward.status == PortForwardStatus::Reconnecting {
+            text = format!("{text} - Reconnecting");
+        }
+        builder = builder.item(
+            &SubmenuBuilder::new(handle, text)
+                .item(&MenuItem::with_id(
+                    handle,
+                    format!("{STOP_PORT_FORWARD_MENU_ID_PREFIX}{}", forward.id),
+                    "Stop",
+                    true,
+                    None::<&str>,
+                )?)
+                .build()?,
+        );
+    }
+    if !forwards.is_empty() {
+        builder = builder.separator();
     }
-    .item(&MenuItem::with_id(
-        handle,
-        "quit",
-        "Quit",
-        true,
-        None::<&str>,
-    )?)
-    .build()
+    builder
+        .item(&MenuItem::with_id(
+            handle,
+            "quit",
+            "Quit",
+            true,
+            None::<&str>,
+        )?)
+        .build()
 }
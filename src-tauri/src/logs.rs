@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use futures::{stream, AsyncBufReadExt, StreamExt};
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::{Api, LogParams};
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::time;
+
+use crate::error::AppError;
+use crate::watcher;
+
+/// Lines emitted at once not to flood the window with events
+const MAX_LINES_PER_EVENT: usize = 500;
+
+/// Interval to emit lines received meanwhile
+const EMIT_INTERVAL_MSEC: u64 = 100;
+
+/// Options of the log stream same as `kubectl logs`
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LogOptions {
+    /// Keep streaming new lines
+    pub follow: bool,
+    /// Number of lines from the end. All lines if not set.
+    pub tail_lines: Option<i64>,
+    /// Logs of the previous terminated container
+    pub previous: bool,
+    /// Prefix each line with RFC 3339 timestamp
+    pub timestamps: bool,
+}
+
+/// Lines of the log stream
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct LogLines {
+    id: u64,
+    lines: Vec<String>,
+}
+
+/// End of the log stream, with the error if failed
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct LogEnd {
+    id: u64,
+    error: Option<AppError>,
+}
+
+/// Log streams running in the background
+#[derive(Default)]
+pub struct LogStreams {
+    next_id: u64,
+    streams: HashMap<u64, JoinHandle<()>>,
+}
+
+/// Stream logs of the container as "log-lines" events until "log-end".
+///
+/// Returns the id of the stream to stop it.
+#[tauri::command]
+pub async fn start_log_stream(
+    app_handle: tauri::AppHandle,
+    context: String,
+    namespace: String,
+    pod_name: String,
+    container_name: String,
+    options: LogOptions,
+) -> Result<u64, AppError> {
+    let client = watcher::client(&context).await?;
+    let api: Api<Pod> = Api::namespaced(client, &namespace);
+    let params = LogParams {
+        container: Some(container_name),
+        follow: options.follow,
+        tail_lines: options.tail_lines,
+        previous: options.previous,
+        timestamps: options.timestamps,
+        ..Default::default()
+    };
+    // Fail here if the container has no logs (ex: no previous container)
+    let reader = api.log_stream(&pod_name, &params).await?;
+    let state = app_handle.state::<Mutex<LogStreams>>();
+    let mut state = state.lock().unwrap();
+    let id = state.next_id;
+    state.next_id += 1;
+    let handle = app_handle.clone();
+    let join_handle = tauri::async_runtime::spawn(async move {
+        // Read raw lines not to end the stream by a line which is not valid UTF-8
+        let mut lines = stream::unfold(Box::pin(reader), |mut reader| async move {
+            let mut line = Vec::new();
+            match reader.read_until(b'\n', &mut line).await {
+                Ok(0) => None,
+                Ok(_) => {
+                    let line = line.strip_suffix(b"\n").unwrap_or(&line);
+                    let line = line.strip_suffix(b"\r").unwrap_or(line);
+                    Some((Ok(String::from_utf8_lossy(line).to_string()), reader))
+                }
+                Err(e) => Some((Err(e), reader)),
+            }
+        })
+        .boxed();
+        let mut buffer: Vec<String> = Vec::new();
+        let mut interval = time::interval(time::Duration::from_millis(EMIT_INTERVAL_MSEC));
+        let error = loop {
+            tokio::select! {
+                line = lines.next() => match line {
+                    Some(Ok(line)) => {
+                        buffer.push(line);
+                        if buffer.len() >= MAX_LINES_PER_EVENT
+                            && emit_lines(&handle, id, &mut buffer).is_err()
+                        {
+                            break None;
+                        }
+                    }
+                    Some(Err(e)) => break Some(AppError::Other(e.to_string())),
+                    None => break None,
+                },
+                _ = interval.tick() => {
+                    if emit_lines(&handle, id, &mut buffer).is_err() {
+                        break None;
+                    }
+                }
+            }
+        };
+        let _ = emit_lines(&handle, id, &mut buffer);
+        handle
+            .state::<Mutex<LogStreams>>()
+            .lock()
+            .unwrap()
+            .streams
+            .remove(&id);
+        if let Err(e) = handle.emit_to("hugill", "log-end", LogEnd { id, error }) {
+            println!("Failed to emit log end: {e}");
+        }
+    });
+    state.streams.insert(id, join_handle);
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn stop_log_stream(app_handle: tauri::AppHandle, id: u64) {
+    if let Some(join_handle) = app_handle
+        .state::<Mutex<LogStreams>>()
+        .lock()
+        .unwrap()
+        .streams
+        .remove(&id)
+    {
+        join_handle.abort();
+    }
+}
+
+/// Emit buffered lines. Fails if the window is closed (ex: while quitting the app).
+fn emit_lines(handle: &AppHandle, id: u64, buffer: &mut Vec<String>) -> tauri::Result<()> {
+    if buffer.is_empty() {
+        return Ok(());
+    }
+    let lines = LogLines {
+        id,
+        lines: std::mem::take(buffer),
+    };
+    handle
+        .emit_to("hugill", "log-lines", lines)
+        .inspect_err(|e| println!("Failed to emit log lines: {e}"))
+}
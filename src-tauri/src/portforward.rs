@@ -0,0 +1,447 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::Mutex;
+
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::Api;
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tokio::time;
+
+use crate::error::AppError;
+use crate::settings::SettingsStore;
+use crate::watcher::{self, ClusterStatus, ContainerState, PodStatus, Workload};
+use crate::workspace::{self, WorkspaceSetting, WorkspaceTarget};
+use crate::AppStatus;
+
+/// Interval to accept connections again after failed (ex: too many open files)
+const ACCEPT_RETRY_INTERVAL_MSEC: u64 = 1000;
+
+/// Pair of ports to forward from localhost to the container
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PortForwardSetting {
+    pub local_port: u16,
+    pub remote_port: u16,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PortForwardStatus {
+    Forwarding,
+    /// The pod is stopped and its replacement is not running yet
+    Reconnecting,
+}
+
+/// Port forward listed in the UI and the tray
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PortForward {
+    pub id: u64,
+    pub context: String,
+    pub namespace: String,
+    /// Pod which connections are forwarded to. Replaced when the pod is restarted.
+    pub pod_name: String,
+    pub container_name: String,
+    pub local_port: u16,
+    pub remote_port: u16,
+    pub status: PortForwardStatus,
+    /// Last error of forwarding the connection
+    pub message: Option<String>,
+}
+
+struct Forward {
+    status: PortForward,
+    /// Labels and workload of the current pod to find its workspace, which may be edited meanwhile
+    labels: BTreeMap<String, String>,
+    workload: Option<Workload>,
+    pod_name: watch::Sender<String>,
+    join_handle: JoinHandle<()>,
+}
+
+/// Port forwards running in the background
+#[derive(Default)]
+pub struct PortForwardManager {
+    next_id: u64,
+    forwards: Vec<Forward>,
+}
+
+impl PortForwardManager {
+    pub fn list(&self) -> Vec<PortForward> {
+        self.forwards
+            .iter()
+            .map(|forward| forward.status.clone())
+            .collect()
+    }
+}
+
+/// Container to forward ports to
+pub struct ForwardTarget<'a> {
+    pub context: &'a str,
+    pub namespace: &'a str,
+    pub pod_name: &'a str,
+    pub container_name: &'a str,
+    pub labels: &'a BTreeMap<String, String>,
+    pub workload: Option<&'a Workload>,
+}
+
+/// Listen on the local port and forward each connection to the port of the pod
+pub async fn start(
+    handle: &AppHandle,
+    target: &ForwardTarget<'_>,
+    ports: PortForwardSetting,
+) -> Result<PortForward, AppError> {
+    let local_port = ports.local_port;
+    if local_port == 0 || ports.remote_port == 0 {
+        return Err(AppError::InvalidInput(
+            "Port must be between 1 and 65535".to_string(),
+        ));
+    }
+    let client = watcher::client(target.context).await?;
+    let listener = TcpListener::bind(("127.0.0.1", local_port))
+        .await
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::AddrInUse => {
+                AppError::PortInUse(format!("Port {local_port} is already in use"))
+            }
+            _ => AppError::Other(format!("Failed to listen on port {local_port}: {e}")),
+        })?;
+    let api: Api<Pod> = Api::namespaced(client, target.namespace);
+    let status = {
+        let state = handle.state::<Mutex<PortForwardManager>>();
+        let mut state = state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        let (pod_name, pod_name_rx) = watch::channel(target.pod_name.to_string());
+        let join_handle = tauri::async_runtime::spawn(serve(
+            handle.clone(),
+            id,
+            listener,
+            api,
+            pod_name_rx,
+            ports.remote_port,
+        ));
+        let status = PortForward {
+            id,
+            context: target.context.to_string(),
+            namespace: target.namespace.to_string(),
+            pod_name: target.pod_name.to_string(),
+            container_name: target.container_name.to_string(),
+            local_port,
+            remote_port: ports.remote_port,
+            status: PortForwardStatus::Forwarding,
+            message: None,
+        };
+        state.forwards.push(Forward {
+            status: status.clone(),
+            labels: target.labels.clone(),
+            workload: target.workload.cloned(),
+            pod_name,
+            join_handle,
+        });
+        status
+    };
+    println!(
+        "Forward localhost:{local_port} to {}:{}",
+        target.pod_name, ports.remote_port
+    );
+    emit_port_forwards(handle);
+    Ok(status)
+}
+
+/// Start port forwards of the workspace unless the local port is already forwarded.
+///
+/// Returns errors of the port forwards failed to start.
+pub async fn start_workspace(
+    handle: &AppHandle,
+    target: &ForwardTarget<'_>,
+    workspace: &WorkspaceSetting,
+) -> Vec<AppError> {
+    let mut errors = Vec::new();
+    for ports in workspace.port_forwards.iter() {
+        let forwarded = handle
+            .state::<Mutex<PortForwardManager>>()
+            .lock()
+            .unwrap()
+            .forwards
+            .iter()
+            .any(|forward| forward.status.local_port == ports.local_port);
+        if forwarded {
+            continue;
+        }
+        if let Err(e) = start(handle, target, *ports).await {
+            println!("Failed to forward port {}: {e}", ports.local_port);
+            errors.push(e);
+        }
+    }
+    errors
+}
+
+/// Stop the port forward and close its connections
+pub fn stop(handle: &AppHandle, id: u64) -> Result<(), AppError> {
+    {
+        let state = handle.state::<Mutex<PortForwardManager>>();
+        let mut state = state.lock().unwrap();
+        let index = state
+            .forwards
+            .iter()
+            .position(|forward| forward.status.id == id)
+            .ok_or(AppError::NotFound("Port forward not found".to_string()))?;
+        state.forwards.remove(index).join_handle.abort();
+    }
+    emit_port_forwards(handle);
+    Ok(())
+}
+
+/// Stop all port forwards before the app exits
+pub fn stop_all(handle: &AppHandle) {
+    let state = handle.state::<Mutex<PortForwardManager>>();
+    let mut state = state.lock().unwrap();
+    for forward in state.forwards.drain(..) {
+        forward.join_handle.abort();
+    }
+}
+
+/// Follow pods restarted in the cluster.
+///
+/// Connections are forwarded to the running pod matched by the same workspace after the pod is replaced.
+/// Without the workspace, wait for the pod of the same name.
+pub fn follow_pods(handle: &AppHandle, cluster: &ClusterStatus) {
+    // Find workspaces every time to follow the selector edited after starting the port forward
+    let workspaces = handle
+        .state::<Mutex<SettingsStore>>()
+        .lock()
+        .unwrap()
+        .app_settings()
+        .workspaces;
+    let changed = {
+        let state = handle.state::<Mutex<PortForwardManager>>();
+        let mut state = state.lock().unwrap();
+        let mut changed = false;
+        for forward in state
+            .forwards
+            .iter_mut()
+            .filter(|forward| forward.status.context == cluster.context)
+        {
+            let status = &forward.status;
+            let is_running = |pod: &&PodStatus| {
+                pod.namespace == status.namespace
+                    && !pod.terminating
+                    && pod.containers.iter().any(|container| {
+                        container.name == status.container_name
+                            && matches!(container.state, ContainerState::Running { .. })
+                    })
+            };
+            let mut running = cluster.pods.iter().filter(is_running);
+            let workspace = workspace::find_workspace(
+                &workspaces,
+                &WorkspaceTarget {
+                    context: &status.context,
+                    namespace: &status.namespace,
+                    container_name: &status.container_name,
+                    labels: &forward.labels,
+                    workload: forward.workload.as_ref(),
+                },
+            )
+            .map(|(_, ws)| ws);
+            let pod = match workspace {
+                // Stick to the current pod while it is running
+                Some(ws) => running
+                    .clone()
+                    .find(|pod| pod.name == status.pod_name)
+                    .or_else(|| {
+                        running.find(|pod| {
+                            ws.matches(&WorkspaceTarget {
+                                context: &cluster.context,
+                                namespace: &pod.namespace,
+                                container_name: &status.container_name,
+                                labels: &pod.labels,
+                                workload: pod.workload.as_ref(),
+                            })
+                        })
+                    }),
+                None => running.find(|pod| pod.name == status.pod_name),
+            };
+            match pod {
+                Some(pod) if pod.name != status.pod_name => {
+                    println!(
+                        "Forward localhost:{} to {} instead of {}",
+                        status.local_port, pod.name, status.pod_name
+                    );
+                    forward.pod_name.send_replace(pod.name.clone());
+                    forward.status.pod_name = pod.name.clone();
+                    forward.labels = pod.labels.clone();
+                    forward.workload = pod.workload.clone();
+                    forward.status.status = PortForwardStatus::Forwarding;
+                    forward.status.message = None;
+                    changed = true;
+                }
+                Some(_) if status.status == PortForwardStatus::Reconnecting => {
+                    forward.status.status = PortForwardStatus::Forwarding;
+                    forward.status.message = None;
+                    changed = true;
+                }
+                None if status.status == PortForwardStatus::Forwarding => {
+                    forward.status.status = PortForwardStatus::Reconnecting;
+                    changed = true;
+                }
+                _ => {}
+            }
+        }
+        changed
+    };
+    if changed {
+        emit_port_forwards(handle);
+    }
+}
+
+async fn serve(
+    handle: AppHandle,
+    id: u64,
+    listener: TcpListener,
+    api: Api<Pod>,
+    pod_name: watch::Receiver<String>,
+    remote_port: u16,
+) {
+    // Aborted together with this task when the port forward is stopped
+    let mut connections = JoinSet::new();
+    // Report the error once until the connection is accepted again
+    let mut failing = false;
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => {
+                failing = false;
+                stream
+            }
+            Err(e) => {
+                if !failing {
+                    println!("Failed to accept the connection: {e}");
+                    set_message(&handle, id, format!("Failed to accept the connection: {e}"));
+                    failing = true;
+                }
+                time::sleep(time::Duration::from_millis(ACCEPT_RETRY_INTERVAL_MSEC)).await;
+                continue;
+            }
+        };
+        while connections.try_join_next().is_some() {}
+        let handle = handle.clone();
+        let api = api.clone();
+        let pod_name = pod_name.borrow().clone();
+        connections.spawn(async move {
+            if let Err(e) = forward(&api, &pod_name, remote_port, stream).await {
+                println!("Failed to forward the connection to {pod_name}: {e}");
+                set_message(&handle, id, e.to_string());
+            }
+        });
+    }
+}
+
+async fn forward(
+    api: &Api<Pod>,
+    pod_name: &str,
+    port: u16,
+    mut stream: TcpStream,
+) -> Result<(), AppError> {
+    let mut forwarder = api.portforward(pod_name, &[port]).await?;
+    let mut upstream = forwarder
+        .take_stream(port)
+        .ok_or(AppError::Other(format!("Port {port} is not forwarded")))?;
+    tokio::io::copy_bidirectional(&mut stream, &mut upstream)
+        .await
+        .map_err(|e| AppError::Other(e.to_string()))?;
+    drop(upstream);
+    forwarder
+        .join()
+        .await
+        .map_err(|e| AppError::Other(e.to_string()))
+}
+
+fn set_message(handle: &AppHandle, id: u64, message: String) {
+    {
+        let state = handle.state::<Mutex<PortForwardManager>>();
+        let mut state = state.lock().unwrap();
+        let Some(forward) = state
+            .forwards
+            .iter_mut()
+            .find(|forward| forward.status.id == id)
+        else {
+            return;
+        };
+        forward.status.message = Some(message);
+    }
+    emit_port_forwards(handle);
+}
+
+/// Emit port forwards to the window and rebuild the tray menu
+fn emit_port_forwards(handle: &AppHandle) {
+    let forwards = handle
+        .state::<Mutex<PortForwardManager>>()
+        .lock()
+        .unwrap()
+        .list();
+    // ex: the window is closed while quitting the app
+    if let Err(e) = handle.emit_to("hugill", "port-forwards", forwards) {
+        println!("Failed to emit port forwards: {e}");
+    }
+    crate::update_tray_menu(handle);
+}
+
+#[tauri::command]
+pub fn list_port_forwards(app_handle: tauri::AppHandle) -> Vec<PortForward> {
+    app_handle
+        .state::<Mutex<PortForwardManager>>()
+        .lock()
+        .unwrap()
+        .list()
+}
+
+/// Forward the local port to the container, following its replacement by the workspace
+#[tauri::command]
+pub async fn start_port_forward(
+    app_handle: tauri::AppHandle,
+    context: String,
+    namespace: String,
+    pod_name: String,
+    container_name: String,
+    local_port: u16,
+    remote_port: u16,
+) -> Result<PortForward, AppError> {
+    let pod = app_handle
+        .state::<Mutex<AppStatus>>()
+        .lock()
+        .unwrap()
+        .cluster_statuses
+        .get(&context)
+        .and_then(|cluster| {
+            cluster
+                .pods
+                .iter()
+                .find(|pod| pod.namespace == namespace && pod.name == pod_name)
+                .cloned()
+        })
+        .ok_or(AppError::NotFound(format!("Pod {pod_name} is not found")))?;
+    start(
+        &app_handle,
+        &ForwardTarget {
+            context: &context,
+            namespace: &namespace,
+            pod_name: &pod_name,
+            container_name: &container_name,
+            labels: &pod.labels,
+            workload: pod.workload.as_ref(),
+        },
+        PortForwardSetting {
+            local_port,
+            remote_port,
+        },
+    )
+    .await
+}
+
+#[tauri::command]
+pub fn stop_port_forward(app_handle: tauri::AppHandle, id: u64) -> Result<(), AppError> {
+    stop(&app_handle, id)
+}
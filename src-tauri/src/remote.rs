@@ -0,0 +1,331 @@
+use k8s_openapi::api::core::v1::Pod;
+use kube::{
+    api::{Api, AttachParams},
+    client::UpgradeConnectionError,
+    Client,
+};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::error::AppError;
+use crate::launcher::OpenMode;
+use crate::watcher;
+
+/// Files which mark the directory as the root of the project
+const PROJECT_MARKERS: [&str; 11] = [
+    ".git",
+    ".devcontainer",
+    "package.json",
+    "Cargo.toml",
+    "go.mod",
+    "pyproject.toml",
+    "requirements.txt",
+    "Gemfile",
+    "pom.xml",
+    "build.gradle",
+    "composer.json",
+];
+
+/// List entries of the directory given as `$1`, with project markers given as the rest of arguments.
+///
+/// Each line is "d<TAB>name<TAB>marker..." for directories and "f<TAB>name" for others.
+/// Exits with [`MISSING_DIRECTORY_MESSAGE`] in stderr if `$1` is not a directory.
+const LIST_DIRECTORY_SCRIPT: &str = r#"
+[ -d "$1" ] || { echo "No such directory" >&2; exit 1; }
+cd -- "$1" || exit 1
+shift
+for f in .* *; do
+    case "$f" in .|..) continue ;; esac
+    [ -e "$f" ] || [ -L "$f" ] || continue
+    if [ -d "$f" ]; then
+        printf 'd\t%s' "$f"
+        for m in "$@"; do
+            [ -e "$f/$m" ] && printf '\t%s' "$m"
+        done
+        for w in "$f"/*.code-workspace; do
+            [ -e "$w" ] && printf '\t%s' "${w##*/}"
+        done
+        printf '\n'
+    else
+        printf 'f\t%s\n' "$f"
+    fi
+done
+exit 0
+"#;
+
+/// Message of [`LIST_DIRECTORY_SCRIPT`] if the directory is missing, not to depend on messages of shells
+const MISSING_DIRECTORY_MESSAGE: &str = "No such directory";
+
+/// Entry of the directory in the container
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteEntry {
+    pub name: String,
+    /// Absolute path in the container
+    pub path: String,
+    pub is_dir: bool,
+    /// Project files found in the directory (ex: ".git", "package.json")
+    pub markers: Vec<String>,
+}
+
+/// Where the suggested workspace folder comes from
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FolderSource {
+    /// `workingDir` of the container in the pod spec
+    WorkingDir,
+    /// Working directory of PID 1, which is usually `WORKDIR` of the image.
+    ///
+    /// Not available if the pod shares the process namespace (PID 1 is the pause container)
+    /// or the container has no `readlink` (ex: distroless images).
+    ProcessWorkingDir,
+    /// `mountPath` of the volume mounted to the container
+    VolumeMount,
+}
+
+/// Candidate of the workspace folder
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderSuggestion {
+    pub path: String,
+    pub source: FolderSource,
+}
+
+/// Mount paths of volumes managed by Kubernetes or the system, not the source code
+const IGNORED_MOUNT_PREFIXES: [&str; 7] =
+    ["/var/run", "/run", "/etc", "/dev", "/proc", "/sys", "/tmp"];
+
+/// Output of the command executed in the container
+pub struct ExecOutput {
+    pub success: bool,
+    /// Reason of the failure. "NonZeroExitCode" if the command exits with non-zero code.
+    pub reason: Option<String>,
+    /// Message of the failure (ex: executable file not found)
+    pub message: Option<String>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Execute the command in the container and wait until it exits
+pub async fn exec(
+    client: Client,
+    namespace: &str,
+    pod_name: &str,
+    container_name: &str,
+    command: Vec<String>,
+) -> Result<ExecOutput, kube::Error> {
+    let api: Api<Pod> = Api::namespaced(client, namespace);
+    let params = AttachParams::default()
+        .container(container_name)
+        .stdin(false)
+        .stdout(true)
+        .stderr(true);
+    let mut process = api.exec(pod_name, command, &params).await?;
+    let status = process.take_status();
+    // Read both at once not to block the command by the full buffer of the other
+    let (stdout, stderr) = tokio::join!(read_all(process.stdout()), read_all(process.stderr()));
+    let (stdout, stderr) = (stdout?, stderr?);
+    let status = match status {
+        Some(status) => status.await,
+        None => None,
+    };
+    process
+        .join()
+        .await
+        .map_err(|e| kube::Error::Service(e.into()))?;
+    Ok(ExecOutput {
+        success: status
+            .as_ref()
+            .is_some_and(|status| status.status.as_deref() == Some("Success")),
+        reason: status.as_ref().and_then(|status| status.reason.clone()),
+        message: status.and_then(|status| status.message),
+        stdout,
+        stderr,
+    })
+}
+
+async fn read_all(reader: Option<impl AsyncRead + Unpin>) -> Result<String, kube::Error> {
+    let mut output = String::new();
+    if let Some(mut reader) = reader {
+        reader
+            .read_to_string(&mut output)
+            .await
+            .map_err(kube::Error::ReadEvents)?;
+    }
+    Ok(output)
+}
+
+/// List entries of the directory in the container, directories first
+#[tauri::command]
+pub async fn list_remote_directory(
+    context: String,
+    namespace: String,
+    pod_name: String,
+    container_name: String,
+    path: String,
+) -> Result<Vec<RemoteEntry>, AppError> {
+    if !path.starts_with('/') {
+        return Err(AppError::InvalidInput(
+            "Path must be an absolute path".to_string(),
+        ));
+    }
+    let mut command: Vec<String> = vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        LIST_DIRECTORY_SCRIPT.to_string(),
+        "sh".to_string(),
+        path.clone(),
+    ];
+    command.extend(PROJECT_MARKERS.iter().map(|marker| marker.to_string()));
+    let client = watcher::client(&context).await?;
+    let output = exec(client, &namespace, &pod_name, &container_name, command).await?;
+    if !output.success {
+        let stderr = output.stderr.trim();
+        if output.reason.as_deref() == Some("NonZeroExitCode")
+            && stderr == MISSING_DIRECTORY_MESSAGE
+        {
+            return Err(AppError::PathNotFound(format!(
+                "{path} is not found in {container_name}"
+            )));
+        }
+        // ex: "sh" is not available in distroless images, or the permission is denied
+        let detail = if stderr.is_empty() {
+            output.message.unwrap_or_default()
+        } else {
+            stderr.to_string()
+        };
+        return Err(AppError::Other(format!("Failed to list {path}: {detail}")));
+    }
+    let mut entries: Vec<RemoteEntry> = output
+        .stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let is_dir = fields.next()? == "d";
+            let name = fields.next()?.to_string();
+            Some(RemoteEntry {
+                path: format!("{}/{name}", path.trim_end_matches('/')),
+                name,
+                is_dir,
+                markers: fields.map(|marker| marker.to_string()).collect(),
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    Ok(entries)
+}
+
+/// Suggest workspace folders of the container, the most likely first
+#[tauri::command]
+pub async fn suggest_workspace_folders(
+    context: String,
+    namespace: String,
+    pod_name: String,
+    container_name: String,
+) -> Result<Vec<FolderSuggestion>, AppError> {
+    let client = watcher::client(&context).await?;
+    let api: Api<Pod> = Api::namespaced(client.clone(), &namespace);
+    let pod = api.get(&pod_name).await?;
+    let spec = pod.spec.as_ref();
+    // Debug containers are opened same as other containers
+    let (working_dir, volume_mounts) = spec
+        .iter()
+        .flat_map(|spec| spec.containers.iter())
+        .find(|container| container.name == container_name)
+        .map(|container| (&container.working_dir, &container.volume_mounts))
+        .or_else(|| {
+            spec.iter()
+                .flat_map(|spec| spec.ephemeral_containers.iter().flatten())
+                .find(|container| container.name == container_name)
+                .map(|container| (&container.working_dir, &container.volume_mounts))
+        })
+        .ok_or(AppError::NotFound(format!(
+            "Container {container_name} is not found in {pod_name}"
+        )))?;
+    let mut suggestions: Vec<FolderSuggestion> = Vec::new();
+    let mut suggest = |path: &str, source: FolderSource| {
+        let path = path.trim().trim_end_matches('/');
+        if path.starts_with('/') && !suggestions.iter().any(|s| s.path == path) {
+            suggestions.push(FolderSuggestion {
+                path: path.to_string(),
+                source,
+            });
+        }
+    };
+    if let Some(working_dir) = working_dir {
+        suggest(working_dir, FolderSource::WorkingDir);
+    }
+    // The image config is not available from the API server, so ask PID 1 of the container instead
+    let share_process_namespace = spec
+        .and_then(|spec| spec.share_process_namespace)
+        .unwrap_or(false);
+    if !share_process_namespace {
+        let command = ["readlink", "/proc/1/cwd"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        match exec(client, &namespace, &pod_name, &container_name, command).await {
+            Ok(output) if output.success => {
+                suggest(&output.stdout, FolderSource::ProcessWorkingDir)
+            }
+            Ok(output) => println!("Failed to read the working directory: {}", output.stderr),
+            Err(e) => println!("Failed to read the working directory: {e}"),
+        }
+    }
+    for volume_mount in volume_mounts.iter().flatten() {
+        // Compare whole segments not to ignore "/devel" by "/dev"
+        let path = volume_mount.mount_path.trim_end_matches('/');
+        if !IGNORED_MOUNT_PREFIXES
+            .iter()
+            .any(|prefix| path == *prefix || path.starts_with(&format!("{prefix}/")))
+        {
+            suggest(&volume_mount.mount_path, FolderSource::VolumeMount);
+        }
+    }
+    Ok(suggestions)
+}
+
+/// Check that the workspace folder or the workspace file exists in the container.
+///
+/// Returns the reason if the check is skipped.
+pub async fn check_path(
+    context: &str,
+    namespace: &str,
+    pod_name: &str,
+    container_name: &str,
+    path: &str,
+    open_mode: OpenMode,
+) -> Result<Option<String>, AppError> {
+    let test = match open_mode {
+        OpenMode::Folder => "-d",
+        OpenMode::File => "-f",
+    };
+    let command = ["test", test, path]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    let client = watcher::client(context).await?;
+    match exec(client, namespace, pod_name, container_name, command).await {
+        Ok(output) if output.success => Ok(None),
+        Ok(output) if output.reason.as_deref() == Some("NonZeroExitCode") => Err(
+            AppError::PathNotFound(format!("{path} is not found in {container_name}")),
+        ),
+        // ex: "test" is not available in distroless images
+        Ok(output) => {
+            let skipped = format!(
+                "Skipped checking {path}: {}",
+                output.message.unwrap_or_default()
+            );
+            println!("{skipped}");
+            Ok(Some(skipped))
+        }
+        Err(kube::Error::UpgradeConnection(UpgradeConnectionError::ProtocolSwitch(status)))
+            if status.as_u16() == 400 =>
+        {
+            Err(AppError::ContainerNotRunning(format!(
+                "{container_name} is not running"
+            )))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
@@ -1,10 +1,180 @@
 use std::sync::Arc;
 
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tauri::Wry;
 use tauri_plugin_store::Store;
 
-use crate::{AppSettings, WorkspaceSetting};
+use crate::{
+    debug::DEFAULT_DEBUG_IMAGE,
+    external_terminal::{CustomTerminal, TerminalApp},
+    launcher::{CustomEditor, Editor},
+    terminal::DEFAULT_TERMINAL_SHELL,
+    watcher::WatchMode,
+    workspace::{WorkspaceSetting, DEFAULT_IGNORED_LABEL_KEYS},
+    AppSettings,
+};
+
+/// Names of contexts or namespaces in settings.json.
+///
+/// Accepts a single name or a list of names. `"*"` means all of them.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Selection {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Selection {
+    pub fn is_all(&self) -> bool {
+        match self {
+            Selection::One(name) => name == "*",
+            Selection::Many(names) => names.iter().any(|name| name == "*"),
+        }
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        match self {
+            Selection::One(name) => vec![name.clone()],
+            Selection::Many(names) => names.clone(),
+        }
+    }
+}
+
+/// Settings editable in the settings page.
+///
+/// Workspaces are managed by their own commands.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneralSettings {
+    pub contexts: Option<Selection>,
+    pub namespace: Option<Selection>,
+    pub watch_mode: WatchMode,
+    pub poll_interval_msec: u64,
+    pub code_command: Option<String>,
+    pub editor: Editor,
+    pub custom_editor: Option<CustomEditor>,
+    pub ignored_label_keys: Vec<String>,
+    pub check_workspace_folder: bool,
+    pub terminal_shell: String,
+    pub terminal_app: TerminalApp,
+    pub custom_terminal: Option<CustomTerminal>,
+    pub debug_image: String,
+}
+
+/// Lower limit of `poll_interval_msec` not to overload the API server
+const MIN_POLL_INTERVAL_MSEC: u64 = 1000;
+
+impl GeneralSettings {
+    /// Validate settings with the names of contexts in kubeconfig
+    pub fn validate(&self, kube_contexts: &[String]) -> Result<(), String> {
+        if let Some(contexts) = self.contexts.as_ref().filter(|c| !c.is_all()) {
+            if contexts.names().is_empty() {
+                return Err("Select at least one context".to_string());
+            }
+            if let Some(context) = contexts
+                .names()
+                .into_iter()
+                .find(|context| !kube_contexts.contains(context))
+            {
+                return Err(format!("Context \"{context}\" is not found in kubeconfig"));
+            }
+        }
+        if let Some(namespace) = self.namespace.as_ref().filter(|n| !n.is_all()) {
+            if namespace.names().is_empty() {
+                return Err("Enter at least one namespace".to_string());
+            }
+            if let Some(namespace) = namespace
+                .names()
+                .into_iter()
+                .find(|namespace| !is_valid_namespace(namespace))
+            {
+                return Err(format!("\"{namespace}\" is not a valid namespace"));
+            }
+        }
+        if self.poll_interval_msec < MIN_POLL_INTERVAL_MSEC {
+            return Err(format!(
+                "Poll interval must be at least {MIN_POLL_INTERVAL_MSEC} msec"
+            ));
+        }
+        if self
+            .code_command
+            .as_ref()
+            .is_some_and(|code_command| code_command.trim().is_empty())
+        {
+            return Err("Command of VS Code must not be empty".to_string());
+        }
+        if self
+            .ignored_label_keys
+            .iter()
+            .any(|key| key.trim().is_empty() || key == "*")
+        {
+            return Err("Ignored label keys must not be empty".to_string());
+        }
+        if self.terminal_shell.trim().is_empty() {
+            return Err("Shell of the terminal must not be empty".to_string());
+        }
+        // The shell is run as a single command by `command -v`, not split into arguments
+        if self.terminal_shell.trim().contains(char::is_whitespace) {
+            return Err("Shell of the terminal must be a command without arguments".to_string());
+        }
+        if self.debug_image.trim().is_empty() {
+            return Err("Image of the debug container must not be empty".to_string());
+        }
+        if self.editor == Editor::Custom {
+            match &self.custom_editor {
+                Some(custom_editor) if !custom_editor.command.trim().is_empty() => {}
+                _ => return Err("Enter the command of the custom editor".to_string()),
+            }
+        }
+        if self.terminal_app == TerminalApp::Custom {
+            match &self.custom_terminal {
+                Some(custom_terminal) if !custom_terminal.command.trim().is_empty() => {}
+                _ => return Err("Enter the command of the custom terminal".to_string()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the cluster watcher has to be restarted to apply the settings
+    pub fn requires_restart_watcher(&self, other: &GeneralSettings) -> bool {
+        self.contexts != other.contexts
+            || self.namespace != other.namespace
+            || self.watch_mode != other.watch_mode
+            || self.poll_interval_msec != other.poll_interval_msec
+    }
+}
+
+/// Namespace must be a DNS label (RFC 1123)
+fn is_valid_namespace(namespace: &str) -> bool {
+    !namespace.is_empty()
+        && namespace.len() <= 63
+        && namespace
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !namespace.starts_with('-')
+        && !namespace.ends_with('-')
+}
+
+impl From<AppSettings> for GeneralSettings {
+    fn from(settings: AppSettings) -> Self {
+        Self {
+            contexts: settings.contexts,
+            namespace: settings.namespace,
+            watch_mode: settings.watch_mode,
+            poll_interval_msec: settings.poll_interval_msec,
+            code_command: settings.code_command,
+            editor: settings.editor,
+            custom_editor: settings.custom_editor,
+            ignored_label_keys: settings.ignored_label_keys,
+            check_workspace_folder: settings.check_workspace_folder,
+            terminal_shell: settings.terminal_shell,
+            terminal_app: settings.terminal_app,
+            custom_terminal: settings.custom_terminal,
+            debug_image: settings.debug_image,
+        }
+    }
+}
 
 pub struct SettingsStore {
     store: Arc<Store<Wry>>,
@@ -18,10 +188,18 @@ impl From<Arc<Store<Wry>>> for SettingsStore {
 
 impl SettingsStore {
     pub fn app_settings(&self) -> AppSettings {
+        let contexts = self
+            .store
+            .get("contexts")
+            .and_then(|contexts| serde_json::from_value::<Selection>(contexts).ok());
         let namespace = self
             .store
             .get("namespace")
-            .and_then(|namespace| serde_json::from_value::<String>(namespace).ok());
+            .and_then(|namespace| serde_json::from_value::<Selection>(namespace).ok());
+        let watch_mode = self
+            .store
+            .get("watch_mode")
+            .and_then(|watch_mode| serde_json::from_value::<WatchMode>(watch_mode).ok());
         let poll_interval_msec = self
             .store
             .get("poll_interval_msec")
@@ -33,17 +211,98 @@ impl SettingsStore {
         let code_command = self.store.get("code_command");
         let code_command = code_command
             .and_then(|code_command| serde_json::from_value::<String>(code_command).ok());
+        let editor = self
+            .store
+            .get("editor")
+            .and_then(|editor| serde_json::from_value::<Editor>(editor).ok());
+        let custom_editor = self
+            .store
+            .get("custom_editor")
+            .and_then(|custom_editor| serde_json::from_value::<CustomEditor>(custom_editor).ok());
+        let ignored_label_keys = self
+            .store
+            .get("ignored_label_keys")
+            .and_then(|keys| serde_json::from_value::<Vec<String>>(keys).ok());
+        let check_workspace_folder = self
+            .store
+            .get("check_workspace_folder")
+            .and_then(|check| serde_json::from_value::<bool>(check).ok());
+        let terminal_shell = self
+            .store
+            .get("terminal_shell")
+            .and_then(|shell| serde_json::from_value::<String>(shell).ok())
+            // Ignore the shell with arguments edited by hand, same as invalid values of other keys
+            .filter(|shell| !shell.trim().contains(char::is_whitespace));
+        let terminal_app = self
+            .store
+            .get("terminal_app")
+            .and_then(|terminal_app| serde_json::from_value::<TerminalApp>(terminal_app).ok());
+        let custom_terminal = self
+            .store
+            .get("custom_terminal")
+            .and_then(|custom_terminal| {
+                serde_json::from_value::<CustomTerminal>(custom_terminal).ok()
+            });
+        let debug_image = self
+            .store
+            .get("debug_image")
+            .and_then(|image| serde_json::from_value::<String>(image).ok());
         AppSettings {
+            contexts,
             namespace,
+            watch_mode: watch_mode.unwrap_or_default(),
             poll_interval_msec: poll_interval_msec.unwrap_or(5000),
             workspaces: workspaces.unwrap_or_default(),
-            code_command: code_command.unwrap_or(
-                "/Applications/Visual Studio Code.app/Contents/Resources/app/bin/code".to_string(),
-            ),
+            code_command,
+            editor: editor.unwrap_or_default(),
+            custom_editor,
+            ignored_label_keys: ignored_label_keys.unwrap_or_else(|| {
+                DEFAULT_IGNORED_LABEL_KEYS
+                    .iter()
+                    .map(|key| key.to_string())
+                    .collect()
+            }),
+            check_workspace_folder: check_workspace_folder.unwrap_or(true),
+            terminal_shell: terminal_shell.unwrap_or(DEFAULT_TERMINAL_SHELL.to_string()),
+            terminal_app: terminal_app.unwrap_or_default(),
+            custom_terminal,
+            debug_image: debug_image.unwrap_or(DEFAULT_DEBUG_IMAGE.to_string()),
         }
     }
 
     pub fn update_workspaces(&self, workspaces: Vec<WorkspaceSetting>) {
         self.store.set("workspaces", json!(workspaces));
     }
+
+    pub fn update_general_settings(&self, settings: &GeneralSettings) {
+        self.set_or_delete("contexts", settings.contexts.as_ref());
+        self.set_or_delete("namespace", settings.namespace.as_ref());
+        self.store.set("watch_mode", json!(settings.watch_mode));
+        self.store
+            .set("poll_interval_msec", json!(settings.poll_interval_msec));
+        self.set_or_delete("code_command", settings.code_command.as_ref());
+        self.store.set("editor", json!(settings.editor));
+        self.set_or_delete("custom_editor", settings.custom_editor.as_ref());
+        self.store
+            .set("ignored_label_keys", json!(settings.ignored_label_keys));
+        self.store.set(
+            "check_workspace_folder",
+            json!(settings.check_workspace_folder),
+        );
+        self.store
+            .set("terminal_shell", json!(settings.terminal_shell));
+        self.store.set("terminal_app", json!(settings.terminal_app));
+        self.set_or_delete("custom_terminal", settings.custom_terminal.as_ref());
+        self.store.set("debug_image", json!(settings.debug_image));
+    }
+
+    /// Delete the key to use the default value if the value is not set
+    fn set_or_delete<T: Serialize>(&self, key: &str, value: Option<&T>) {
+        match value {
+            Some(value) => self.store.set(key, json!(value)),
+            None => {
+                self.store.delete(key);
+            }
+        }
+    }
 }
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures::SinkExt;
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::{Api, AttachParams, AttachedProcess, TerminalSize};
+use serde::Serialize;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+use crate::error::AppError;
+use crate::settings::SettingsStore;
+use crate::watcher;
+
+/// Shell of the terminal unless `terminal_shell` is set in settings.json
+pub const DEFAULT_TERMINAL_SHELL: &str = "/bin/bash";
+
+/// Run the shell given as `$0`, falling back to `/bin/sh` if it is not found in the container
+pub const SHELL_SCRIPT: &str =
+    r#"if command -v "$0" >/dev/null 2>&1; then exec "$0"; else exec /bin/sh; fi"#;
+
+/// Size of the buffer to read the output of the terminal
+const OUTPUT_BUFFER_SIZE: usize = 8192;
+
+enum TerminalInput {
+    Data(Vec<u8>),
+    Resize(TerminalSize),
+}
+
+/// Output of the terminal
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct TerminalOutput {
+    id: u64,
+    /// Base64 encoded bytes, which is smaller than the JSON array of numbers
+    data: String,
+}
+
+/// Exit of the shell, with the error if failed
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct TerminalExit {
+    id: u64,
+    error: Option<AppError>,
+}
+
+struct TerminalSession {
+    input: mpsc::UnboundedSender<TerminalInput>,
+    join_handle: JoinHandle<()>,
+    process: AttachedProcess,
+}
+
+/// Terminals attached to containers
+#[derive(Default)]
+pub struct TerminalSessions {
+    next_id: u64,
+    sessions: HashMap<u64, TerminalSession>,
+}
+
+/// Open the shell in the container with the tty.
+///
+/// Output is emitted as "terminal-output" until "terminal-exit". Returns the id of the terminal.
+#[tauri::command]
+pub async fn open_terminal(
+    app_handle: tauri::AppHandle,
+    context: String,
+    namespace: String,
+    pod_name: String,
+    container_name: String,
+    cols: u16,
+    rows: u16,
+) -> Result<u64, AppError> {
+    let shell = app_handle
+        .state::<Mutex<SettingsStore>>()
+        .lock()
+        .unwrap()
+        .app_settings()
+        .terminal_shell;
+    let client = watcher::client(&context).await?;
+    let api: Api<Pod> = Api::namespaced(client, &namespace);
+    let command = ["sh", "-c", SHELL_SCRIPT, &shell];
+    let params = AttachParams::interactive_tty().container(&container_name);
+    let mut process = api.exec(&pod_name, command, &params).await?;
+    let (Some(mut stdin), Some(mut stdout), Some(mut terminal_size)) =
+        (process.stdin(), process.stdout(), process.terminal_size())
+    else {
+        return Err(AppError::Other(format!(
+            "Failed to attach to {container_name}"
+        )));
+    };
+    let status = process.take_status();
+    let _ = terminal_size
+        .send(TerminalSize {
+            width: cols,
+            height: rows,
+        })
+        .await;
+    let (input, mut input_rx) = mpsc::unbounded_channel();
+    let state = app_handle.state::<Mutex<TerminalSessions>>();
+    let mut state = state.lock().unwrap();
+    let id = state.next_id;
+    state.next_id += 1;
+    let handle = app_handle.clone();
+    let join_handle = tauri::async_runtime::spawn(async move {
+        let mut buffer = vec![0u8; OUTPUT_BUFFER_SIZE];
+        let mut error = loop {
+            tokio::select! {
+                read = stdout.read(&mut buffer) => match read {
+                    Ok(0) => break None,
+                    Ok(n) => {
+                        let output = TerminalOutput {
+                            id,
+                            data: STANDARD.encode(&buffer[..n]),
+                        };
+                        // ex: the window is closed while quitting the app
+                        if let Err(e) = handle.emit_to("hugill", "terminal-output", output) {
+                            println!("Failed to emit terminal output: {e}");
+                            break None;
+                        }
+                    }
+                    Err(e) => break Some(AppError::Other(e.to_string())),
+                },
+                input = input_rx.recv() => match input {
+                    Some(TerminalInput::Data(data)) => {
+                        if let Err(e) = stdin.write_all(&data).await {
+                            break Some(AppError::Other(e.to_string()));
+                        }
+                    }
+                    Some(TerminalInput::Resize(size)) => {
+                        let _ = terminal_size.send(size).await;
+                    }
+                    None => break None,
+                },
+            }
+        };
+        // ex: "command terminated with non-zero exit code"
+        if let Some(status) = status {
+            if let Some(status) = status.await {
+                if error.is_none() && status.status.as_deref() == Some("Failure") {
+                    error = status.message.map(AppError::Other);
+                }
+            }
+        }
+        handle
+            .state::<Mutex<TerminalSessions>>()
+            .lock()
+            .unwrap()
+            .sessions
+            .remove(&id);
+        if let Err(e) = handle.emit_to("hugill", "terminal-exit", TerminalExit { id, error }) {
+            println!("Failed to emit terminal exit: {e}");
+        }
+    });
+    state.sessions.insert(
+        id,
+        TerminalSession {
+            input,
+            join_handle,
+            process,
+        },
+    );
+    println!("Open terminal of {pod_name} ({container_name}) with {shell}");
+    Ok(id)
+}
+
+/// Send the input of the user to the terminal
+#[tauri::command]
+pub fn write_terminal(app_handle: tauri::AppHandle, id: u64, data: String) -> Result<(), AppError> {
+    send(&app_handle, id, TerminalInput::Data(data.into_bytes()))
+}
+
+/// Propagate the size of the terminal in the window to the tty
+#[tauri::command]
+pub fn resize_terminal(
+    app_handle: tauri::AppHandle,
+    id: u64,
+    cols: u16,
+    rows: u16,
+) -> Result<(), AppError> {
+    send(
+        &app_handle,
+        id,
+        TerminalInput::Resize(TerminalSize {
+            width: cols,
+            height: rows,
+        }),
+    )
+}
+
+/// Close the connection to the container, which hangs up the shell
+#[tauri::command]
+pub fn close_terminal(app_handle: tauri::AppHandle, id: u64) {
+    if let Some(session) = app_handle
+        .state::<Mutex<TerminalSessions>>()
+        .lock()
+        .unwrap()
+        .sessions
+        .remove(&id)
+    {
+        session.join_handle.abort();
+        session.process.abort();
+    }
+}
+
+fn send(handle: &AppHandle, id: u64, input: TerminalInput) -> Result<(), AppError> {
+    handle
+        .state::<Mutex<TerminalSessions>>()
+        .lock()
+        .unwrap()
+        .sessions
+        .get(&id)
+        .and_then(|session| session.input.send(input).ok())
+        .ok_or(AppError::NotFound("Terminal is closed".to_string()))
+}
@@ -1,18 +1,136 @@
-use std::collections::BTreeMap;
-use std::sync::Mutex;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Arc, Mutex};
 
-use k8s_openapi::api::core::v1::Pod;
+use futures::{stream, StreamExt};
+use k8s_openapi::api::apps::v1::ReplicaSet;
+use k8s_openapi::api::batch::v1::Job;
+use k8s_openapi::api::core::v1::{ContainerPort, Pod};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
 use kube::{
     api::{Api, ListParams, ResourceExt},
     config::{Config, KubeConfigOptions, Kubeconfig},
+    runtime::{reflector, reflector::Store, watcher, WatchStreamExt},
     Client,
 };
 use serde::{Deserialize, Serialize};
 use tauri::async_runtime::JoinHandle;
 use tauri::{AppHandle, Emitter, EventTarget, Manager};
-use tokio::time;
+use tokio::{sync::watch, time};
 
-use crate::settings::SettingsStore;
+use crate::error::AppError;
+use crate::launcher::Editor;
+use crate::portforward::PortForwardSetting;
+use crate::settings::{Selection, SettingsStore};
+use crate::workspace::{self, WorkspaceSetting, WorkspaceTarget};
+
+/// State of the container.
+///
+/// See: https://kubernetes.io/docs/concepts/workloads/pods/pod-lifecycle/#container-states
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all_fields = "camelCase")]
+pub enum ContainerState {
+    Running {
+        /// RFC 3339 time when the container started
+        started_at: Option<String>,
+    },
+    Waiting {
+        /// ex: "ContainerCreating", "CrashLoopBackOff", "ImagePullBackOff"
+        reason: Option<String>,
+        message: Option<String>,
+    },
+    Terminated {
+        /// ex: "Completed", "Error", "OOMKilled"
+        reason: Option<String>,
+        message: Option<String>,
+        exit_code: i32,
+    },
+    Unknown,
+}
+
+/// Reasons of the waiting state which does not recover without any changes
+const FAILED_WAITING_REASONS: [&str; 7] = [
+    "CrashLoopBackOff",
+    "ImagePullBackOff",
+    "ErrImagePull",
+    "InvalidImageName",
+    "CreateContainerConfigError",
+    "CreateContainerError",
+    "RunContainerError",
+];
+
+impl ContainerState {
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            ContainerState::Waiting { reason, .. } | ContainerState::Terminated { reason, .. } => {
+                reason.as_deref()
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the container is failing, not starting slowly
+    pub fn is_failed(&self) -> bool {
+        match self {
+            ContainerState::Waiting { reason, .. } => reason
+                .as_deref()
+                .is_some_and(|reason| FAILED_WAITING_REASONS.contains(&reason)),
+            ContainerState::Terminated { exit_code, .. } => *exit_code != 0,
+            _ => false,
+        }
+    }
+}
+
+impl From<&k8s_openapi::api::core::v1::ContainerState> for ContainerState {
+    fn from(state: &k8s_openapi::api::core::v1::ContainerState) -> Self {
+        if let Some(running) = &state.running {
+            ContainerState::Running {
+                started_at: running.started_at.as_ref().map(|t| t.0.to_rfc3339()),
+            }
+        } else if let Some(waiting) = &state.waiting {
+            ContainerState::Waiting {
+                reason: waiting.reason.clone(),
+                message: waiting.message.clone(),
+            }
+        } else if let Some(terminated) = &state.terminated {
+            ContainerState::Terminated {
+                reason: terminated.reason.clone(),
+                message: terminated.message.clone(),
+                exit_code: terminated.exit_code,
+            }
+        } else {
+            ContainerState::Unknown
+        }
+    }
+}
+
+// Container status
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerStatus {
+    /// Container name
+    pub name: String,
+    pub state: ContainerState,
+    /// Whether the container is failing, same as [`ContainerState::is_failed`]
+    pub failed: bool,
+    /// Reason of the last termination (ex: "OOMKilled") to explain restarts
+    pub last_termination_reason: Option<String>,
+    pub ready: bool,
+    pub restart_count: i32,
+    pub workspace_folder: Option<String>,
+    /// Folders opened recently in the container, the most recently used first
+    pub recent_folders: Vec<String>,
+    /// Editor saved for the workspace of this container
+    pub editor: Option<Editor>,
+    /// Ports declared in the pod spec
+    pub ports: Vec<u16>,
+    /// Ports forwarded for the workspace of this container
+    pub port_forwards: Vec<PortForwardSetting>,
+    /// Whether this is the ephemeral container added to debug the pod
+    pub ephemeral: bool,
+    /// Container whose processes are visible from the ephemeral container
+    pub target_container_name: Option<String>,
+}
 
 // Pod status
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -20,121 +138,563 @@ use crate::settings::SettingsStore;
 pub struct PodStatus {
     /// Pod name
     pub name: String,
-    pub container_name: Option<String>,
-    pub status: String,
+    pub namespace: String,
+    /// ex: "Pending", "Running", "Succeeded", "Failed", "Unknown"
+    pub phase: Option<String>,
+    /// RFC 3339 time when the pod was acknowledged by the kubelet
+    pub start_time: Option<String>,
+    /// Whether the pod is being deleted
+    pub terminating: bool,
+    /// Containers in the order of the pod spec, with the default container first and ephemeral containers last
+    pub containers: Vec<ContainerStatus>,
+    /// Container to open unless specified
+    pub default_container: Option<String>,
     pub labels: BTreeMap<String, String>,
-    pub workspace_folder: Option<String>,
+    /// Workload which owns the pod. `None` for the bare pod.
+    pub workload: Option<Workload>,
+}
+
+/// Workload which owns pods, resolved from owner references
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Workload {
+    /// ex: "Deployment", "StatefulSet", "DaemonSet", "CronJob"
+    pub kind: String,
+    pub name: String,
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.kind, self.name)
+    }
 }
 
+/// Annotation to declare the workspace folder of containers in the pod.
+///
+/// `hugill.mtgto.net/workspace-folder.<container name>` declares it for the specific container.
+const WORKSPACE_FOLDER_ANNOTATION: &str = "hugill.mtgto.net/workspace-folder";
+
+/// Annotation to specify the container used by `kubectl exec`, `kubectl logs` and so on
+const DEFAULT_CONTAINER_ANNOTATION: &str = "kubectl.kubernetes.io/default-container";
+
 // Running pods status
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ClusterStatus {
     pub context: String,
-    pub namespace: String,
+    /// Watched namespaces. `None` means all namespaces.
+    pub namespaces: Option<Vec<String>>,
     pub pods: Vec<PodStatus>,
+    /// Message of the error since `pods` were received, cleared when the watcher recovers
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Delay to emit the status after pods are changed, to emit changes at once
+const EMIT_DELAY_MSEC: u64 = 200;
+
+/// How the watcher receives pod changes from the cluster
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchMode {
+    /// Receive pod changes as they happen by watch API (default)
+    #[default]
+    Watch,
+    /// List all pods every `poll_interval_msec` (fallback for restricted API servers)
+    Poll,
+}
+
+/// Error on watching pods of the context
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WatcherError {
+    pub context: String,
+    /// Same as `code` of `AppError`
+    pub code: String,
+    pub message: String,
+}
+
+/// Contexts in kubeconfig
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct KubeContexts {
+    pub current_context: Option<String>,
+    pub contexts: Vec<String>,
+}
+
+pub fn kube_contexts() -> Result<KubeContexts, AppError> {
+    let kubeconfig = Kubeconfig::read()?;
+    Ok(KubeContexts {
+        current_context: kubeconfig.current_context,
+        contexts: kubeconfig
+            .contexts
+            .into_iter()
+            .map(|context| context.name)
+            .collect(),
+    })
+}
+
+/// Resolve names of kubeconfig contexts to watch.
+///
+/// Use the current context if no contexts are configured.
+pub fn resolve_contexts(contexts: Option<&Selection>) -> Result<Vec<String>, AppError> {
+    let kubeconfig = Kubeconfig::read()?;
+    match contexts {
+        Some(contexts) if contexts.is_all() => Ok(kubeconfig
+            .contexts
+            .into_iter()
+            .map(|context| context.name)
+            .collect()),
+        Some(contexts) => Ok(contexts.names()),
+        None => Ok(vec![kubeconfig.current_context.ok_or(
+            AppError::ContextMissing("Current context is not set in kubeconfig".to_string()),
+        )?]),
+    }
+}
+
+/// Client of the Kubernetes API for the context in kubeconfig
+pub async fn client(context: &str) -> Result<Client, AppError> {
+    let kubeconfig = Kubeconfig::read()?;
+    let options = KubeConfigOptions {
+        context: Some(context.to_string()),
+        ..Default::default()
+    };
+    let config = Config::from_custom_kubeconfig(kubeconfig, &options).await?;
+    Ok(Client::try_from(config)?)
 }
 
 pub fn start(
     handle: AppHandle,
-    namespace: Option<String>,
+    context: String,
+    namespace: Option<Selection>,
+    watch_mode: WatchMode,
     poll_interval_msec: u64,
-) -> Result<JoinHandle<()>, Box<dyn std::error::Error>> {
-    let kubeconfig = Kubeconfig::read()?;
-    let current_context = kubeconfig
-        .current_context
-        .clone()
-        .ok_or("no current context")?;
-    let client = tauri::async_runtime::block_on(async move {
-        let config = Config::from_custom_kubeconfig(kubeconfig, &KubeConfigOptions::default())
-            .await
-            .map_err(|_| "failed to load kubeconfig")?;
-        Client::try_from(config).map_err(|_| "failed to load config for k8s")
-    })?;
-    println!("default context: {}", current_context);
+    refresh: watch::Receiver<()>,
+) -> Result<JoinHandle<()>, AppError> {
+    let client = tauri::async_runtime::block_on(client(&context))?;
+    println!("watch context: {}", context);
+    let resolver = WorkloadResolver::new(client.clone());
     let handle: JoinHandle<()> = tauri::async_runtime::spawn(async move {
-        let namespace = namespace.unwrap_or(client.default_namespace().to_string());
-        let api: Api<Pod> = Api::namespaced(client, &namespace);
-        loop {
-            match api.list(&ListParams::default()).await {
-                Ok(pod_list) => {
-                    let mut pods: Vec<PodStatus> = Vec::new();
-                    for pod in pod_list {
-                        let name = pod.name_any();
-                        let container_name = pod.spec.map(|s| s.containers[0].name.clone());
-                        let labels = pod.metadata.labels.unwrap_or_default();
-                        let workspace_folder = container_name.clone().and_then(|container_name| {
-                            resolve_workspace_folder(
-                                &handle,
-                                &current_context,
-                                &namespace,
-                                &container_name,
-                                &labels,
-                            )
-                        });
-                        pods.push(PodStatus {
-                            name,
-                            container_name,
-                            status: pod
-                                .status
-                                .and_then(|s| s.container_statuses)
-                                .and_then(|cs| cs[0].state.clone())
-                                .map_or("Unknown".to_string(), |s| {
-                                    if s.running.is_some() {
-                                        "Running".to_string()
-                                    } else if s.waiting.is_some() {
-                                        "Waiting".to_string()
-                                    } else if s.terminated.is_some() {
-                                        "Terminated".to_string()
-                                    } else {
-                                        "Unknown".to_string()
-                                    }
-                                }),
-                            labels: labels.clone(),
-                            workspace_folder,
-                        });
+        let namespaces = match namespace {
+            Some(namespace) if namespace.is_all() => None,
+            Some(namespace) => Some(namespace.names()),
+            None => Some(vec![client.default_namespace().to_string()]),
+        };
+        let apis: Vec<Api<Pod>> = match &namespaces {
+            Some(namespaces) => namespaces
+                .iter()
+                .map(|namespace| Api::namespaced(client.clone(), namespace))
+                .collect(),
+            None => vec![Api::all(client)],
+        };
+        match watch_mode {
+            WatchMode::Watch => {
+                watch_pods(&handle, apis, &context, namespaces, refresh, resolver).await
+            }
+            WatchMode::Poll => {
+                poll_pods(
+                    &handle,
+                    apis,
+                    &context,
+                    namespaces,
+                    poll_interval_msec,
+                    refresh,
+                    resolver,
+                )
+                .await
+            }
+        }
+    });
+    Ok(handle)
+}
+
+/// Keep the local cache of pods up to date by watch API and emit the status on every change.
+///
+/// The watcher re-lists pods when the API server returns 410 Gone and retries with backoff on errors.
+/// After errors, the watcher may resume without listing pods again, so cached pods are emitted on the next event.
+/// Cached pods are emitted again when `refresh` is notified, since workspace settings may be changed.
+async fn watch_pods(
+    handle: &AppHandle,
+    apis: Vec<Api<Pod>>,
+    context: &str,
+    namespaces: Option<Vec<String>>,
+    mut refresh: watch::Receiver<()>,
+    mut resolver: WorkloadResolver,
+) {
+    let mut readers: Vec<Store<Pod>> = Vec::new();
+    let mut streams = Vec::new();
+    for (i, api) in apis.into_iter().enumerate() {
+        let (reader, writer) = reflector::store();
+        readers.push(reader);
+        streams.push(
+            watcher(api, watcher::Config::default())
+                .default_backoff()
+                .reflect(writer)
+                .map(move |event| (i, event))
+                .boxed(),
+        );
+    }
+    // Whether the initial listing of each namespace is completed
+    let mut initialized = vec![false; streams.len()];
+    let mut stream = stream::select_all(streams);
+    let mut failed = false;
+    // Coalesce changes until the deadline not to rebuild statuses on every event of busy namespaces
+    let mut deadline: Option<time::Instant> = None;
+    loop {
+        let ready = initialized.iter().all(|initialized| *initialized);
+        tokio::select! {
+            event = stream.next() => match event {
+                Some((i, Ok(event))) => {
+                    if let watcher::Event::InitDone = event {
+                        initialized[i] = true;
+                    }
+                    // Wait until the initial listing of every namespace is completed to avoid emitting partial pods.
+                    // The store keeps the previous pods while listing again, so emit them to clear the error.
+                    let listing = matches!(event, watcher::Event::Init | watcher::Event::InitApply(_));
+                    if initialized.iter().all(|initialized| *initialized) && (failed || !listing) {
+                        failed = false;
+                        deadline.get_or_insert_with(|| time::Instant::now() + time::Duration::from_millis(EMIT_DELAY_MSEC));
                     }
-                    let status = ClusterStatus {
-                        context: current_context.clone(),
-                        namespace: namespace.clone(),
-                        pods,
-                    };
-                    handle
-                        .emit_to(EventTarget::app(), "watcher", status)
-                        .expect("failed to emit watcher event");
                 }
+                Some((_, Err(e))) => {
+                    failed = true;
+                    emit_error(handle, context, e.into());
+                }
+                None => break,
+            },
+            // Emitted on the recovery instead while failing not to clear the error
+            Ok(()) = refresh.changed(), if ready && !failed => {
+                deadline.get_or_insert_with(|| time::Instant::now() + time::Duration::from_millis(EMIT_DELAY_MSEC));
+            }
+            _ = time::sleep_until(deadline.unwrap_or_else(time::Instant::now)), if deadline.is_some() => {
+                deadline = None;
+                emit_cached_status(handle, context, namespaces.clone(), &readers, &mut resolver).await;
+            }
+        }
+    }
+}
+
+async fn emit_cached_status(
+    handle: &AppHandle,
+    context: &str,
+    namespaces: Option<Vec<String>>,
+    readers: &[Store<Pod>],
+    resolver: &mut WorkloadResolver,
+) {
+    let mut pods: Vec<Arc<Pod>> = readers.iter().flat_map(|reader| reader.state()).collect();
+    pods.sort_by_key(|pod| (pod.namespace(), pod.name_any()));
+    let pods: Vec<&Pod> = pods.iter().map(|pod| pod.as_ref()).collect();
+    emit_status(handle, context, namespaces, &pods, resolver).await;
+}
+
+async fn poll_pods(
+    handle: &AppHandle,
+    apis: Vec<Api<Pod>>,
+    context: &str,
+    namespaces: Option<Vec<String>>,
+    poll_interval_msec: u64,
+    mut refresh: watch::Receiver<()>,
+    mut resolver: WorkloadResolver,
+) {
+    loop {
+        let mut pods: Vec<Pod> = Vec::new();
+        let mut error: Option<kube::Error> = None;
+        for api in apis.iter() {
+            match api.list(&ListParams::default()).await {
+                Ok(pod_list) => pods.extend(pod_list),
                 Err(e) => {
-                    handle
-                        .emit_to(EventTarget::app(), "watcher-error", e.to_string())
-                        .expect("failed to emit watcher error event");
+                    error = Some(e);
+                    break;
                 }
             }
-            tokio::time::sleep(time::Duration::from_millis(poll_interval_msec)).await;
         }
-    });
-    Ok(handle)
+        match error {
+            None => {
+                let pods: Vec<&Pod> = pods.iter().collect();
+                emit_status(handle, context, namespaces.clone(), &pods, &mut resolver).await
+            }
+            Some(e) => emit_error(handle, context, e.into()),
+        }
+        // List pods again without waiting the interval if workspace settings are changed
+        tokio::select! {
+            _ = tokio::time::sleep(time::Duration::from_millis(poll_interval_msec)) => {}
+            Ok(()) = refresh.changed() => {}
+        }
+    }
 }
 
-fn resolve_workspace_folder(
+async fn emit_status(
     handle: &AppHandle,
     context: &str,
-    namespace: &str,
+    namespaces: Option<Vec<String>>,
+    pods: &[&Pod],
+    resolver: &mut WorkloadResolver,
+) {
+    let workloads = resolver.resolve(pods).await;
+    // Read settings once, not for every container
+    let workspaces = handle
+        .state::<Mutex<SettingsStore>>()
+        .lock()
+        .unwrap()
+        .app_settings()
+        .workspaces;
+    let status = ClusterStatus {
+        context: context.to_string(),
+        namespaces,
+        pods: pods
+            .iter()
+            .zip(workloads)
+            .map(|(pod, workload)| pod_status(&workspaces, context, pod, workload))
+            .collect(),
+        error: None,
+    };
+    handle
+        .emit_to(EventTarget::app(), "watcher", status)
+        .expect("failed to emit watcher event");
+}
+
+fn emit_error(handle: &AppHandle, context: &str, error: AppError) {
+    let error = WatcherError {
+        context: context.to_string(),
+        code: error.code().to_string(),
+        message: error.to_string(),
+    };
+    handle
+        .emit_to(EventTarget::app(), "watcher-error", error)
+        .expect("failed to emit watcher error event");
+}
+
+fn pod_status(
+    workspaces: &[WorkspaceSetting],
+    context: &str,
+    pod: &Pod,
+    workload: Option<Workload>,
+) -> PodStatus {
+    let name = pod.name_any();
+    let namespace = pod.namespace().unwrap_or_default();
+    let labels = pod.labels().clone();
+    let container_status = |name: &str,
+                            ports: Option<&Vec<ContainerPort>>,
+                            statuses: Option<&Vec<k8s_openapi::api::core::v1::ContainerStatus>>,
+                            ephemeral: bool,
+                            target_container_name: Option<&String>| {
+        // Match by name since container statuses may not be in the order of the pod spec
+        let status = statuses.and_then(|cs| cs.iter().find(|cs| cs.name == name));
+        let workspace = workspace::find_workspace(
+            workspaces,
+            &WorkspaceTarget {
+                context,
+                namespace: &namespace,
+                container_name: name,
+                labels: &labels,
+                workload: workload.as_ref(),
+            },
+        )
+        .map(|(_, ws)| ws);
+        let workspace_folder = resolve_workspace_folder(workspace, name, pod.annotations());
+        let state = status
+            .and_then(|s| s.state.as_ref())
+            .map_or(ContainerState::Unknown, ContainerState::from);
+        ContainerStatus {
+            name: name.to_string(),
+            failed: state.is_failed(),
+            state,
+            last_termination_reason: status
+                .and_then(|s| s.last_state.as_ref())
+                .and_then(|s| s.terminated.as_ref())
+                .and_then(|s| s.reason.clone()),
+            ready: status.is_some_and(|s| s.ready),
+            restart_count: status.map_or(0, |s| s.restart_count),
+            workspace_folder,
+            recent_folders: workspace.map(|ws| ws.recent_folders()).unwrap_or_default(),
+            editor: workspace.and_then(|ws| ws.editor),
+            ports: ports
+                .into_iter()
+                .flatten()
+                .filter_map(|port| u16::try_from(port.container_port).ok())
+                .collect(),
+            port_forwards: workspace
+                .map(|ws| ws.port_forwards.clone())
+                .unwrap_or_default(),
+            ephemeral,
+            target_container_name: target_container_name.cloned(),
+        }
+    };
+    let mut containers: Vec<ContainerStatus> = pod
+        .spec
+        .iter()
+        .flat_map(|s| s.containers.iter())
+        .map(|container| {
+            container_status(
+                &container.name,
+                container.ports.as_ref(),
+                pod.status
+                    .as_ref()
+                    .and_then(|s| s.container_statuses.as_ref()),
+                false,
+                None,
+            )
+        })
+        .collect();
+    // Prefer the annotated container, falling back to the first container
+    let default_container = pod
+        .annotations()
+        .get(DEFAULT_CONTAINER_ANNOTATION)
+        .filter(|name| containers.iter().any(|container| &container.name == *name))
+        .cloned()
+        .or_else(|| containers.first().map(|container| container.name.clone()));
+    if let Some(default_container) = &default_container {
+        containers.sort_by_key(|container| &container.name != default_container);
+    }
+    // Ephemeral containers added by `kubectl debug` or Hugill
+    containers.extend(
+        pod.spec
+            .iter()
+            .flat_map(|s| s.ephemeral_containers.iter().flatten())
+            .map(|container| {
+                container_status(
+                    &container.name,
+                    container.ports.as_ref(),
+                    pod.status
+                        .as_ref()
+                        .and_then(|s| s.ephemeral_container_statuses.as_ref()),
+                    true,
+                    container.target_container_name.as_ref(),
+                )
+            }),
+    );
+    PodStatus {
+        name,
+        namespace,
+        phase: pod.status.as_ref().and_then(|s| s.phase.clone()),
+        start_time: pod
+            .status
+            .as_ref()
+            .and_then(|s| s.start_time.as_ref())
+            .map(|t| t.0.to_rfc3339()),
+        terminating: pod.metadata.deletion_timestamp.is_some(),
+        containers,
+        default_container,
+        labels,
+        workload,
+    }
+}
+
+/// Resolve the workspace folder of the container.
+///
+/// Workspace settings saved by the user take precedence over pod annotations.
+fn resolve_workspace_folder(
+    workspace: Option<&WorkspaceSetting>,
     container_name: &str,
-    labels: &BTreeMap<String, String>,
+    annotations: &BTreeMap<String, String>,
 ) -> Option<String> {
-    let settings_store = handle.state::<Mutex<SettingsStore>>();
-    let settings_store = settings_store.lock().unwrap();
-    let settings = settings_store.app_settings();
-    settings.workspaces.iter().find_map(|ws| {
-        if ws.context == context && ws.namespace == namespace && ws.container_name == container_name
-        {
-            let satisfied = ws
-                .labels
-                .iter()
-                .all(|(k, v)| labels.get(k).map(|val| val == v).unwrap_or(false));
-            if satisfied {
-                return Some(ws.workspace_folder.clone());
+    workspace.map(|ws| ws.workspace_folder.clone()).or_else(|| {
+        annotations
+            .get(&format!("{WORKSPACE_FOLDER_ANNOTATION}.{container_name}"))
+            .or_else(|| annotations.get(WORKSPACE_FOLDER_ANNOTATION))
+            .filter(|workspace_folder| workspace_folder.starts_with('/'))
+            .cloned()
+    })
+}
+
+/// Resolve workloads of pods by following owner references.
+///
+/// Owners of ReplicaSets and Jobs are fetched from the API server and cached while pods owned by them exist.
+struct WorkloadResolver {
+    client: Client,
+    /// Workloads keyed by namespace, kind and name of the owner of pods
+    cache: HashMap<(String, String, String), Workload>,
+}
+
+impl WorkloadResolver {
+    fn new(client: Client) -> Self {
+        Self {
+            client,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Workload of each pod in the same order
+    async fn resolve(&mut self, pods: &[&Pod]) -> Vec<Option<Workload>> {
+        let mut cache = HashMap::new();
+        let mut workloads = Vec::new();
+        for pod in pods {
+            let Some(owner) = controller_of(pod.metadata.owner_references.as_deref()) else {
+                workloads.push(None);
+                continue;
+            };
+            let key = (
+                pod.namespace().unwrap_or_default(),
+                owner.kind.clone(),
+                owner.name.clone(),
+            );
+            let workload = match self.cache.get(&key).or_else(|| cache.get(&key)) {
+                Some(workload) => workload.clone(),
+                None => self
+                    .fetch_owner(&key.0, owner)
+                    .await
+                    .unwrap_or_else(|| fallback_workload(pod, owner)),
+            };
+            cache.insert(key, workload.clone());
+            workloads.push(Some(workload));
+        }
+        // Forget owners which no longer own any pods
+        self.cache = cache;
+        workloads
+    }
+
+    /// Fetch the owner of the ReplicaSet or the Job. `None` if failed.
+    async fn fetch_owner(&self, namespace: &str, owner: &OwnerReference) -> Option<Workload> {
+        let owner_references = match owner.kind.as_str() {
+            "ReplicaSet" => Api::<ReplicaSet>::namespaced(self.client.clone(), namespace)
+                .get(&owner.name)
+                .await
+                .map(|replica_set| replica_set.metadata.owner_references),
+            "Job" => Api::<Job>::namespaced(self.client.clone(), namespace)
+                .get(&owner.name)
+                .await
+                .map(|job| job.metadata.owner_references),
+            _ => Ok(None),
+        };
+        match owner_references {
+            Ok(owner_references) => Some(
+                controller_of(owner_references.as_deref())
+                    .map_or_else(|| workload_of(owner), workload_of),
+            ),
+            Err(e) => {
+                println!(
+                    "Failed to get {} {namespace}/{}: {e}",
+                    owner.kind, owner.name
+                );
+                None
             }
         }
-        None
-    })
+    }
+}
+
+fn controller_of(owner_references: Option<&[OwnerReference]>) -> Option<&OwnerReference> {
+    owner_references?
+        .iter()
+        .find(|owner| owner.controller == Some(true))
+}
+
+fn workload_of(owner: &OwnerReference) -> Workload {
+    Workload {
+        kind: owner.kind.clone(),
+        name: owner.name.clone(),
+    }
+}
+
+/// Guess the workload when the owner cannot be fetched (ex: no permission to get ReplicaSets)
+fn fallback_workload(pod: &Pod, owner: &OwnerReference) -> Workload {
+    // ReplicaSet of the Deployment is named "<deployment>-<pod-template-hash>"
+    let deployment = pod
+        .labels()
+        .get("pod-template-hash")
+        .filter(|_| owner.kind == "ReplicaSet")
+        .and_then(|hash| owner.name.strip_suffix(&format!("-{hash}")));
+    match deployment {
+        Some(name) => Workload {
+            kind: "Deployment".to_string(),
+            name: name.to_string(),
+        },
+        None => workload_of(owner),
+    }
 }
@@ -0,0 +1,451 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use crate::error::AppError;
+use crate::launcher::{Editor, OpenMode};
+use crate::portforward::PortForwardSetting;
+use crate::settings::SettingsStore;
+use crate::watcher::Workload;
+use crate::AppStatus;
+
+/// Label keys which change on every deploy and should not be used to match workspaces.
+///
+/// A key starting with `*` matches keys ending with the rest (ex: `*-hash`).
+pub const DEFAULT_IGNORED_LABEL_KEYS: [&str; 5] = [
+    "*-hash",
+    "pod-template-generation",
+    "version",
+    "app.kubernetes.io/version",
+    "helm.sh/chart",
+];
+
+/// Whether the label key is in the ignore list
+pub fn is_ignored_label_key(key: &str, ignored_label_keys: &[String]) -> bool {
+    ignored_label_keys
+        .iter()
+        .any(|ignored| match ignored.strip_prefix('*') {
+            Some(suffix) => key.ends_with(suffix),
+            None => key == ignored,
+        })
+}
+
+/// Operator of the label selector requirement
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LabelSelectorOperator {
+    In,
+    NotIn,
+    Exists,
+    DoesNotExist,
+}
+
+/// Requirement of the label selector same as `matchExpressions` of Kubernetes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LabelSelectorRequirement {
+    pub key: String,
+    pub operator: LabelSelectorOperator,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+}
+
+impl LabelSelectorRequirement {
+    pub fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        let value = labels.get(&self.key);
+        match self.operator {
+            LabelSelectorOperator::In => value.is_some_and(|value| self.values.contains(value)),
+            LabelSelectorOperator::NotIn => !value.is_some_and(|value| self.values.contains(value)),
+            LabelSelectorOperator::Exists => value.is_some(),
+            LabelSelectorOperator::DoesNotExist => value.is_none(),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.key.is_empty() {
+            return Err("Key of the label selector must not be empty".to_string());
+        }
+        match self.operator {
+            LabelSelectorOperator::In | LabelSelectorOperator::NotIn if self.values.is_empty() => {
+                Err(format!("Values of \"{}\" must not be empty", self.key))
+            }
+            LabelSelectorOperator::Exists | LabelSelectorOperator::DoesNotExist
+                if !self.values.is_empty() =>
+            {
+                Err(format!("Values of \"{}\" must be empty", self.key))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WorkspaceSetting {
+    pub context: String,
+    pub namespace: String,
+    pub container_name: String,
+    /// Folder opened last time
+    pub workspace_folder: String,
+    /// Whether `workspace_folder` is a folder or a `.code-workspace` file
+    #[serde(default)]
+    pub open_mode: OpenMode,
+    /// Folders and `.code-workspace` files opened recently, the most recently used first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recent_folders: Vec<String>,
+    /// Labels which the pod must have, same as `matchLabels` of Kubernetes
+    pub labels: HashMap<String, String>,
+    /// Requirements which the labels of the pod must satisfy in addition to `labels`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub match_expressions: Vec<LabelSelectorRequirement>,
+    /// Workload which the pod must be owned by
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workload: Option<Workload>,
+    /// Editor to open this workspace. Use `editor` in settings.json if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub editor: Option<Editor>,
+    /// Ports forwarded from localhost to the container
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub port_forwards: Vec<PortForwardSetting>,
+    /// Start `port_forwards` when the workspace is opened in the editor
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub auto_start_port_forwards: bool,
+}
+
+/// Container of the pod to find the workspace for
+pub struct WorkspaceTarget<'a> {
+    pub context: &'a str,
+    pub namespace: &'a str,
+    pub container_name: &'a str,
+    pub labels: &'a BTreeMap<String, String>,
+    pub workload: Option<&'a Workload>,
+}
+
+impl WorkspaceSetting {
+    pub fn matches(&self, target: &WorkspaceTarget) -> bool {
+        self.context == target.context
+            && self.namespace == target.namespace
+            && self.container_name == target.container_name
+            && self
+                .workload
+                .as_ref()
+                .is_none_or(|workload| target.workload == Some(workload))
+            && self
+                .labels
+                .iter()
+                .all(|(k, v)| target.labels.get(k).map(|val| val == v).unwrap_or(false))
+            && self
+                .match_expressions
+                .iter()
+                .all(|requirement| requirement.matches(target.labels))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.context.is_empty() || self.namespace.is_empty() || self.container_name.is_empty() {
+            return Err("Context, namespace and container name are required".to_string());
+        }
+        if !self.workspace_folder.starts_with('/') {
+            return Err("Workspace folder must be an absolute path".to_string());
+        }
+        for (i, ports) in self.port_forwards.iter().enumerate() {
+            if ports.local_port == 0 || ports.remote_port == 0 {
+                return Err("Port must be between 1 and 65535".to_string());
+            }
+            if self.port_forwards[..i]
+                .iter()
+                .any(|other| other.local_port == ports.local_port)
+            {
+                return Err(format!(
+                    "Local port {} is forwarded more than once",
+                    ports.local_port
+                ));
+            }
+        }
+        self.match_expressions
+            .iter()
+            .try_for_each(LabelSelectorRequirement::validate)
+    }
+}
+
+/// Number of recent folders kept for each workspace
+const MAX_RECENT_FOLDERS: usize = 10;
+
+impl WorkspaceSetting {
+    /// Recent folders including `workspace_folder` at the first
+    pub fn recent_folders(&self) -> Vec<String> {
+        let mut folders = vec![self.workspace_folder.clone()];
+        folders.extend(
+            self.recent_folders
+                .iter()
+                .filter(|folder| **folder != self.workspace_folder)
+                .cloned(),
+        );
+        folders.truncate(MAX_RECENT_FOLDERS);
+        folders
+    }
+
+    /// Make the folder the most recently used one
+    pub fn use_folder(&mut self, folder: &str) {
+        // Keep the mode of the same folder, which may be edited in settings.json
+        if folder != self.workspace_folder {
+            self.open_mode = OpenMode::of(folder);
+        }
+        self.workspace_folder = folder.to_string();
+        self.recent_folders = self.recent_folders();
+    }
+}
+
+/// Find the workspace of the container and its index.
+///
+/// Workspaces keyed by the workload take precedence over workspaces keyed by labels only.
+pub fn find_workspace<'a>(
+    workspaces: &'a [WorkspaceSetting],
+    target: &WorkspaceTarget,
+) -> Option<(usize, &'a WorkspaceSetting)> {
+    workspaces
+        .iter()
+        .enumerate()
+        .filter(|(_, ws)| ws.matches(target))
+        .min_by_key(|(_, ws)| ws.workload.is_none())
+}
+
+/// Container of the live pod matched by the workspace setting
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceMatch {
+    pub context: String,
+    pub namespace: String,
+    pub pod_name: String,
+    pub container_name: String,
+    /// False if another workspace takes precedence for this container
+    pub effective: bool,
+}
+
+#[tauri::command]
+pub fn list_workspaces(app_handle: tauri::AppHandle) -> Vec<WorkspaceSetting> {
+    app_handle
+        .state::<Mutex<SettingsStore>>()
+        .lock()
+        .unwrap()
+        .app_settings()
+        .workspaces
+}
+
+#[tauri::command]
+pub fn update_workspace(
+    app_handle: tauri::AppHandle,
+    index: usize,
+    workspace: WorkspaceSetting,
+) -> Result<(), AppError> {
+    workspace.validate().map_err(AppError::InvalidInput)?;
+    if let Some(folder) = workspace
+        .recent_folders
+        .iter()
+        .find(|folder| !folder.starts_with('/'))
+    {
+        return Err(AppError::InvalidInput(format!(
+            "Recent folder \"{folder}\" must be an absolute path"
+        )));
+    }
+    modify_workspaces(&app_handle, |workspaces| {
+        let ws = workspaces.get_mut(index).ok_or(workspace_not_found())?;
+        let previous = std::mem::replace(ws, workspace);
+        // The dialog does not send the mode, so keep the saved one unless the folder is changed
+        ws.open_mode = if ws.workspace_folder == previous.workspace_folder {
+            previous.open_mode
+        } else {
+            OpenMode::of(&ws.workspace_folder)
+        };
+        ws.recent_folders = ws.recent_folders();
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn delete_workspace(app_handle: tauri::AppHandle, index: usize) -> Result<(), AppError> {
+    modify_workspaces(&app_handle, |workspaces| {
+        if index >= workspaces.len() {
+            return Err(workspace_not_found());
+        }
+        workspaces.remove(index);
+        Ok(())
+    })
+}
+
+/// Insert the copy of the workspace next to it
+#[tauri::command]
+pub fn duplicate_workspace(app_handle: tauri::AppHandle, index: usize) -> Result<(), AppError> {
+    modify_workspaces(&app_handle, |workspaces| {
+        let ws = workspaces.get(index).ok_or(workspace_not_found())?.clone();
+        workspaces.insert(index + 1, ws);
+        Ok(())
+    })
+}
+
+/// List containers of live pods which the workspace matches.
+///
+/// `index` is the position of the workspace being edited. The workspace is appended if it is out of range.
+#[tauri::command]
+pub fn test_workspace(
+    app_handle: tauri::AppHandle,
+    index: usize,
+    workspace: WorkspaceSetting,
+) -> Vec<WorkspaceMatch> {
+    // Replace the saved one with the edited one to find the effective workspace by the same precedence
+    let mut workspaces = list_workspaces(app_handle.clone());
+    let index = match workspaces.get_mut(index) {
+        Some(ws) => {
+            *ws = workspace.clone();
+            index
+        }
+        None => {
+            workspaces.push(workspace.clone());
+            workspaces.len() - 1
+        }
+    };
+    let state = app_handle.state::<Mutex<AppStatus>>();
+    let state = state.lock().unwrap();
+    let mut matches = Vec::new();
+    for cluster in state.cluster_statuses.values() {
+        for pod in cluster.pods.iter() {
+            for container in pod.containers.iter() {
+                let target = WorkspaceTarget {
+                    context: &cluster.context,
+                    namespace: &pod.namespace,
+                    container_name: &container.name,
+                    labels: &pod.labels,
+                    workload: pod.workload.as_ref(),
+                };
+                if workspace.matches(&target) {
+                    let effective =
+                        find_workspace(&workspaces, &target).is_some_and(|(i, _)| i == index);
+                    matches.push(WorkspaceMatch {
+                        context: cluster.context.clone(),
+                        namespace: pod.namespace.clone(),
+                        pod_name: pod.name.clone(),
+                        container_name: container.name.clone(),
+                        effective,
+                    });
+                }
+            }
+        }
+    }
+    matches
+}
+
+/// Save modified workspaces and emit cluster statuses with them
+fn modify_workspaces(
+    app_handle: &tauri::AppHandle,
+    f: impl FnOnce(&mut Vec<WorkspaceSetting>) -> Result<(), AppError>,
+) -> Result<(), AppError> {
+    {
+        let settings_store = app_handle.state::<Mutex<SettingsStore>>();
+        let settings_store = settings_store.lock().unwrap();
+        let mut workspaces = settings_store.app_settings().workspaces;
+        f(&mut workspaces)?;
+        settings_store.update_workspaces(workspaces);
+    }
+    crate::refresh_cluster_statuses(app_handle);
+    Ok(())
+}
+
+fn workspace_not_found() -> AppError {
+    AppError::NotFound("Workspace not found".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn requirement(
+        key: &str,
+        operator: LabelSelectorOperator,
+        values: &[&str],
+    ) -> LabelSelectorRequirement {
+        LabelSelectorRequirement {
+            key: key.to_string(),
+            operator,
+            values: values.iter().map(|value| value.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn ignored_label_key() {
+        let ignored: Vec<String> = DEFAULT_IGNORED_LABEL_KEYS
+            .iter()
+            .map(|key| key.to_string())
+            .collect();
+        assert!(is_ignored_label_key("pod-template-hash", &ignored));
+        assert!(is_ignored_label_key("controller-revision-hash", &ignored));
+        assert!(is_ignored_label_key("version", &ignored));
+        assert!(!is_ignored_label_key("app", &ignored));
+        // Keys without `*` match exactly
+        assert!(!is_ignored_label_key("app.version", &ignored));
+        assert!(!is_ignored_label_key("hash", &ignored));
+        assert!(is_ignored_label_key("anything", &["*".to_string()]));
+    }
+
+    #[test]
+    fn requirement_in() {
+        let req = requirement("env", LabelSelectorOperator::In, &["dev", "stg"]);
+        assert!(req.matches(&labels(&[("env", "dev")])));
+        assert!(!req.matches(&labels(&[("env", "prod")])));
+        assert!(!req.matches(&labels(&[])));
+    }
+
+    #[test]
+    fn requirement_not_in() {
+        let req = requirement("env", LabelSelectorOperator::NotIn, &["prod"]);
+        assert!(req.matches(&labels(&[("env", "dev")])));
+        assert!(!req.matches(&labels(&[("env", "prod")])));
+        // Same as Kubernetes, the missing key satisfies NotIn
+        assert!(req.matches(&labels(&[])));
+    }
+
+    #[test]
+    fn requirement_exists() {
+        let req = requirement("debug", LabelSelectorOperator::Exists, &[]);
+        assert!(req.matches(&labels(&[("debug", "")])));
+        assert!(!req.matches(&labels(&[("app", "web")])));
+    }
+
+    #[test]
+    fn requirement_does_not_exist() {
+        let req = requirement("debug", LabelSelectorOperator::DoesNotExist, &[]);
+        assert!(!req.matches(&labels(&[("debug", "true")])));
+        assert!(req.matches(&labels(&[("app", "web")])));
+    }
+
+    #[test]
+    fn validate_requirement() {
+        assert!(requirement("env", LabelSelectorOperator::In, &["dev"])
+            .validate()
+            .is_ok());
+        assert!(requirement("", LabelSelectorOperator::Exists, &[])
+            .validate()
+            .is_err());
+        assert!(requirement("env", LabelSelectorOperator::In, &[])
+            .validate()
+            .is_err());
+        assert!(requirement("env", LabelSelectorOperator::NotIn, &[])
+            .validate()
+            .is_err());
+        assert!(requirement("env", LabelSelectorOperator::Exists, &["dev"])
+            .validate()
+            .is_err());
+        assert!(
+            requirement("env", LabelSelectorOperator::DoesNotExist, &["dev"])
+                .validate()
+                .is_err()
+        );
+        assert!(requirement("env", LabelSelectorOperator::DoesNotExist, &[])
+            .validate()
+            .is_ok());
+    }
+}
@@ -0,0 +1,102 @@
+import { type AppError, errorMessage } from "$lib/error";
+import type { PortForwardSetting } from "$lib/workspace";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+// https://kubernetes.io/docs/concepts/workloads/pods/pod-lifecycle/#container-states
+export type ContainerState =
+    | { type: "Running"; startedAt?: string }
+    | { type: "Waiting"; reason?: string; message?: string }
+    | { type: "Terminated"; reason?: string; message?: string; exitCode: number }
+    | { type: "Unknown" };
+
+export type ContainerStatus = {
+    name: string;
+    state: ContainerState;
+    // Whether the container is failing, not starting slowly nor completed
+    failed: boolean;
+    lastTerminationReason?: string;
+    ready: boolean;
+    restartCount: number;
+    workspaceFolder?: string;
+    // The most recently used first
+    recentFolders: string[];
+    editor?: string;
+    // Ports declared in the pod spec
+    ports: number[];
+    // Ports forwarded for the workspace of this container
+    portForwards: PortForwardSetting[];
+    // Ephemeral container added to debug the pod
+    ephemeral: boolean;
+    // Container whose processes are visible from the ephemeral container
+    targetContainerName?: string;
+};
+
+// Workload which owns pods (ex: Deployment, StatefulSet)
+export type Workload = {
+    kind: string;
+    name: string;
+};
+
+export type PodStatus = {
+    name: string;
+    namespace: string;
+    phase?: string;
+    startTime?: string;
+    terminating: boolean;
+    containers: ContainerStatus[];
+    defaultContainer?: string;
+    labels: Record<string, string>;
+    // null for the bare pod
+    workload: Workload | null;
+};
+
+export type ClusterStatus = {
+    context: string;
+    // null means all namespaces
+    namespaces: string[] | null;
+    // Pods are the last received ones if the watcher is failing
+    pods: PodStatus[];
+    error: string | null;
+};
+
+type WatcherError = {
+    context: string;
+    code: AppError["code"];
+    message: string;
+};
+
+// Shared by all pages not to lose cluster statuses on page transition
+export const clusterStore = $state<{
+    // Cluster statuses keyed by context
+    clusters: Record<string, ClusterStatus>;
+    error: string | null;
+}>({ clusters: {}, error: null });
+
+let watcherStarted = false;
+
+// Start the cluster watcher unless it has already been started
+export const startClusterWatcher = async () => {
+    if (!watcherStarted) {
+        await invoke("start_cluster_watcher");
+        watcherStarted = true;
+        console.log("Start watching cluster.");
+    }
+};
+
+// Clear cluster statuses when the watcher is restarted by the backend
+export const resetClusters = () => {
+    clusterStore.clusters = {};
+};
+
+listen<ClusterStatus>("cluster-status", (event) => {
+    const clusterStatus = event.payload;
+    console.log("Received cluster status:", clusterStatus);
+    clusterStore.clusters[clusterStatus.context] = clusterStatus;
+});
+
+listen<WatcherError>("cluster-status-error", (event) => {
+    const { context, message } = event.payload;
+    console.error(`Failed to get cluster status of ${context}:`, message);
+    clusterStore.error = `Failed to get cluster status of ${context}: ${errorMessage(event.payload, message)}`;
+});
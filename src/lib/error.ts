@@ -0,0 +1,46 @@
+// Error returned by commands of the backend
+export type AppError = {
+    code:
+        | "kubeconfigMissing"
+        | "contextMissing"
+        | "authFailed"
+        | "apiUnreachable"
+        | "forbidden"
+        | "editorNotFound"
+        | "editorFailed"
+        | "terminalFailed"
+        | "pathNotFound"
+        | "containerNotRunning"
+        | "portInUse"
+        | "notFound"
+        | "invalidInput"
+        | "other";
+    message: string;
+    // Editor, command and locations checked for "editorNotFound"
+    details: { editor: string; app: string; command: string; checked: string[] } | null;
+};
+
+export const isAppError = (error: unknown): error is AppError =>
+    typeof error === "object" && error !== null && "code" in error && "message" in error;
+
+// What the user can do to fix the error
+const hints: Partial<Record<AppError["code"], string>> = {
+    kubeconfigMissing: "Check that ~/.kube/config or KUBECONFIG exists.",
+    contextMissing: "Choose contexts in Settings.",
+    authFailed: "Log in to the cluster again and check the credentials in kubeconfig.",
+    apiUnreachable: "Check the network connection and the server of the context.",
+    forbidden: "Ask the cluster admin for the permission.",
+    // The message tells the setting for the editor
+    editorNotFound: "Install the editor.",
+    portInUse: "Choose another local port.",
+    terminalFailed: "Install the terminal or choose another one in Settings.",
+};
+
+// Message with the hint to show in the notification
+export const errorMessage = (error: unknown, fallback: string): string => {
+    if (!isAppError(error)) {
+        return fallback;
+    }
+    const hint = hints[error.code];
+    return hint ? `${error.message} ${hint}` : error.message;
+};
@@ -0,0 +1,28 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+export type PortForward = {
+    id: number;
+    context: string;
+    namespace: string;
+    // Replaced when the pod is restarted
+    podName: string;
+    containerName: string;
+    localPort: number;
+    remotePort: number;
+    // "reconnecting" while waiting for the replacement pod
+    status: "forwarding" | "reconnecting";
+    // Last error of forwarding the connection
+    message: string | null;
+};
+
+// Shared by all pages not to lose port forwards on page transition
+export const portForwardStore = $state<{ forwards: PortForward[] }>({ forwards: [] });
+
+export const loadPortForwards = async () => {
+    portForwardStore.forwards = await invoke<PortForward[]>("list_port_forwards");
+};
+
+listen<PortForward[]>("port-forwards", (event) => {
+    portForwardStore.forwards = event.payload;
+});
@@ -0,0 +1,97 @@
+import type { Workload } from "$lib/cluster.svelte";
+
+export type LabelSelectorRequirement = {
+    key: string;
+    operator: "In" | "NotIn" | "Exists" | "DoesNotExist";
+    values?: string[];
+};
+
+// Pair of ports forwarded from localhost to the container
+export type PortForwardSetting = {
+    local_port: number;
+    remote_port: number;
+};
+
+// Saved workspace in settings.json
+export type WorkspaceSetting = {
+    context: string;
+    namespace: string;
+    container_name: string;
+    workspace_folder: string;
+    // "file" for the .code-workspace file
+    open_mode?: "folder" | "file";
+    recent_folders?: string[];
+    labels: Record<string, string>;
+    match_expressions?: LabelSelectorRequirement[];
+    workload?: Workload;
+    editor?: string;
+    port_forwards?: PortForwardSetting[];
+    // Start port_forwards when the workspace is opened
+    auto_start_port_forwards?: boolean;
+};
+
+// Container of the live pod matched by the workspace
+export type WorkspaceMatch = {
+    context: string;
+    namespace: string;
+    podName: string;
+    containerName: string;
+    // false if another workspace saved before takes precedence
+    effective: boolean;
+};
+
+// Candidate of the workspace folder suggested by the backend
+export type FolderSuggestion = {
+    path: string;
+    source: "workingDir" | "processWorkingDir" | "volumeMount";
+};
+
+// Same as `is_ignored_label_key` of the backend. "*-hash" matches keys ending with "-hash".
+export const isIgnoredLabelKey = (key: string, ignoredLabelKeys: string[]): boolean =>
+    ignoredLabelKeys.some((ignored) => (ignored.startsWith("*") ? key.endsWith(ignored.slice(1)) : key === ignored));
+
+// Format the requirement in the selector syntax of kubectl (ex: "env in (dev,stg)", "!canary")
+export const formatRequirement = (requirement: LabelSelectorRequirement): string => {
+    const values = (requirement.values ?? []).join(",");
+    switch (requirement.operator) {
+        case "In":
+            return `${requirement.key} in (${values})`;
+        case "NotIn":
+            return `${requirement.key} notin (${values})`;
+        case "Exists":
+            return requirement.key;
+        case "DoesNotExist":
+            return `!${requirement.key}`;
+    }
+};
+
+// Parse the requirement in the selector syntax of kubectl. Returns null if the syntax is invalid.
+export const parseRequirement = (text: string): LabelSelectorRequirement | null => {
+    const setBased = text.trim().match(/^([^\s!(),]+)\s+(in|notin)\s*\(([^)]*)\)$/);
+    if (setBased) {
+        const values = setBased[3]
+            .split(",")
+            .map((value) => value.trim())
+            .filter((value) => value !== "");
+        return { key: setBased[1], operator: setBased[2] === "in" ? "In" : "NotIn", values };
+    }
+    const existence = text.trim().match(/^(!?)([^\s!(),=]+)$/);
+    if (existence) {
+        return { key: existence[2], operator: existence[1] === "!" ? "DoesNotExist" : "Exists" };
+    }
+    return null;
+};
+
+// Format the pair of ports same as kubectl port-forward (ex: "8080:80")
+export const formatPortForward = (ports: PortForwardSetting): string => `${ports.local_port}:${ports.remote_port}`;
+
+// Parse "LOCAL:REMOTE" or "PORT" for the same port. Returns null if the syntax is invalid.
+export const parsePortForward = (text: string): PortForwardSetting | null => {
+    const match = text.trim().match(/^(\d+)(?::(\d+))?$/);
+    if (!match) {
+        return null;
+    }
+    const ports = { local_port: Number(match[1]), remote_port: Number(match[2] ?? match[1]) };
+    const isValid = (port: number) => port >= 1 && port <= 65535;
+    return isValid(ports.local_port) && isValid(ports.remote_port) ? ports : null;
+};